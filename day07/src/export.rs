@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::{Balance, NodeData};
+
+// labels in the input are plain letters, but escape anyway so the output is always valid
+fn escape(s: &str) -> String {
+    let mut result = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+// expects weigh_tree to have been run, so total weights and balance are filled in
+pub fn to_dot(root: &str, graph: &BTreeMap<String, NodeData>) -> String {
+    let mut result = String::new();
+    writeln!(&mut result, "digraph tower {{").unwrap();
+    writeln!(&mut result, "    node [shape=box];").unwrap();
+    let mut stack = vec![root];
+    while let Some(label) = stack.pop() {
+        if let Some(node) = graph.get(label) {
+            let name = escape(label);
+            write!(&mut result,
+                   "    \"{}\" [label=\"{}\\nweight: {}\\ntotal: {}\"",
+                   name,
+                   name,
+                   node.weight,
+                   node.total_weight)
+                .unwrap();
+            if node.balance == Balance::Unbalanced {
+                write!(&mut result, ", color=red, fontcolor=red").unwrap();
+            }
+            writeln!(&mut result, "];").unwrap();
+            for child in node.children.iter() {
+                writeln!(&mut result, "    \"{}\" -> \"{}\";", name, escape(child)).unwrap();
+            }
+            // push in reverse so the children come out in input order
+            stack.extend(node.children.iter().rev().map(|s| s.as_str()));
+        }
+    }
+    writeln!(&mut result, "}}").unwrap();
+    result
+}
+
+fn write_json_node(out: &mut String,
+                   label: &str,
+                   graph: &BTreeMap<String, NodeData>,
+                   indent: usize) {
+    let pad = " ".repeat(indent);
+    writeln!(out, "{{").unwrap();
+    writeln!(out, "{}  \"name\": \"{}\",", pad, escape(label)).unwrap();
+    if let Some(node) = graph.get(label) {
+        writeln!(out, "{}  \"weight\": {},", pad, node.weight).unwrap();
+        writeln!(out, "{}  \"total_weight\": {},", pad, node.total_weight).unwrap();
+        writeln!(out,
+                 "{}  \"balanced\": {},",
+                 pad,
+                 node.balance == Balance::Balanced)
+            .unwrap();
+        if node.children.is_empty() {
+            writeln!(out, "{}  \"children\": []", pad).unwrap();
+        } else {
+            writeln!(out, "{}  \"children\": [", pad).unwrap();
+            for (i, child) in node.children.iter().enumerate() {
+                write!(out, "{}    ", pad).unwrap();
+                write_json_node(out, child, graph, indent + 4);
+                if i + 1 < node.children.len() {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+            writeln!(out, "{}  ]", pad).unwrap();
+        }
+    } else {
+        writeln!(out, "{}  \"children\": []", pad).unwrap();
+    }
+    write!(out, "{}}}", pad).unwrap();
+}
+
+// expects weigh_tree to have been run, so total weights and balance are filled in
+pub fn to_json(root: &str, graph: &BTreeMap<String, NodeData>) -> String {
+    let mut result = String::new();
+    write_json_node(&mut result, root, graph, 0);
+    result.push('\n');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::example;
+    use super::*;
+
+    #[test]
+    fn dot_marks_unbalanced_programs() {
+        let (root, g) = example();
+        let dot = to_dot(&root, &g);
+        let red: Vec<&str> = dot.lines().filter(|l| l.contains("color=red")).collect();
+        assert_eq!(red,
+                   ["    \"tknk\" [label=\"tknk\\nweight: 41\\ntotal: 778\", color=red, fontcolor=red];"]);
+        assert!(dot.contains("    \"ugml\" [label=\"ugml\\nweight: 68\\ntotal: 251\"];\n"));
+        assert!(dot.contains("    \"tknk\" -> \"ugml\";\n    \"tknk\" -> \"padx\";\n    \"tknk\" -> \"fwft\";\n"));
+        assert!(dot.starts_with("digraph tower {\n") && dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn json_nesting() {
+        let (root, g) = example();
+        let json = to_json(&root, &g);
+        // every program with the indentation that shows its depth, and whether it is balanced
+        let names: Vec<&str> = json.lines().filter(|l| l.contains("\"name\"")).collect();
+        let balanced: Vec<&str> = json.lines().filter(|l| l.contains("\"balanced\"")).map(str::trim).collect();
+        assert_eq!(names,
+                   ["  \"name\": \"tknk\",",
+                    "      \"name\": \"ugml\",",
+                    "          \"name\": \"gyxo\",",
+                    "          \"name\": \"ebii\",",
+                    "          \"name\": \"jptl\",",
+                    "      \"name\": \"padx\",",
+                    "          \"name\": \"pbga\",",
+                    "          \"name\": \"havc\",",
+                    "          \"name\": \"qoyq\",",
+                    "      \"name\": \"fwft\",",
+                    "          \"name\": \"ktlj\",",
+                    "          \"name\": \"cntj\",",
+                    "          \"name\": \"xhth\","]);
+        assert_eq!(balanced[0], "\"balanced\": false,");
+        assert!(balanced[1..].iter().all(|&b| b == "\"balanced\": true,"));
+        assert!(json.contains("        {\n          \"name\": \"jptl\",\n          \"weight\": 61,\n          \
+                               \"total_weight\": 61,\n          \"balanced\": true,\n          \
+                               \"children\": []\n        }\n      ]\n    },\n"));
+    }
+}
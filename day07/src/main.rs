@@ -1,10 +1,16 @@
-use std::io::{self, Read};
+use std::io::{self, BufRead, Read};
+use std::fs::File;
+use std::env;
 use std::collections::BTreeMap;
 use std::collections::btree_map::Entry;
 extern crate combine;
 use combine::*;
 use combine::char::{char, digit, letter, spaces, string};
 
+mod export;
+mod tower;
+mod validate;
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 enum Balance {
     Balanced,
@@ -20,6 +26,9 @@ struct NodeData {
     balance: Balance,
 }
 
+// a program as it appears in the input: label, weight and the programs it holds
+type RawNode = (String, i32, Option<Vec<String>>);
+
 impl NodeData {
     fn from_weight(w: i32) -> NodeData {
         NodeData {
@@ -42,13 +51,13 @@ impl NodeData {
     }
 }
 
-fn construct_graph(nodes: Vec<(String, i32, Option<Vec<String>>)>) -> BTreeMap<String, NodeData> {
+fn construct_graph(nodes: Vec<RawNode>) -> BTreeMap<String, NodeData> {
     let mut graph: BTreeMap<String, NodeData> = BTreeMap::new();
     for node in nodes.into_iter() {
         match node.2 {
             Some(children) => {
                 for child in children.iter() {
-                    let mut entry = graph.entry(child.clone()).or_insert(NodeData::from_weight(0));
+                    let entry = graph.entry(child.clone()).or_insert(NodeData::from_weight(0));
                     entry.parent = Some(node.0.clone());
                 }
                 match graph.entry(node.0) {
@@ -71,7 +80,7 @@ fn construct_graph(nodes: Vec<(String, i32, Option<Vec<String>>)>) -> BTreeMap<S
     graph
 }
 
-fn parse_graph(s: &str) -> Option<BTreeMap<String, NodeData>> {
+fn parse_nodes(s: &str) -> Option<Vec<RawNode>> {
     let ident = many1(letter()).skip(spaces());
     let num = between(char('(').skip(spaces()), char(')'), many1(digit()))
         .skip(spaces())
@@ -80,10 +89,9 @@ fn parse_graph(s: &str) -> Option<BTreeMap<String, NodeData>> {
                  sep_by1(ident.clone(), (char(','), spaces())).skip(spaces()))
         .map(|(_, v)| v);
     let node = (ident, num.skip(spaces()), optional(edges));
-    let mut nodes = (spaces(), many1(node).skip(spaces()), eof())
-        .map(|(_, nodes, _)| construct_graph(nodes));
+    let mut nodes = (spaces(), many1(node).skip(spaces()), eof()).map(|(_, nodes, _)| nodes);
     match nodes.parse(State::new(s)) {
-        Ok((graph, _)) => Some(graph),
+        Ok((nodes, _)) => Some(nodes),
         Err(err) => {
             println!("{}", err);
             None
@@ -221,11 +229,113 @@ fn balance_tree_helper<'a>(node: &'a NodeData,
     }
 }
 
+// parses and validates a tower, returning its root and the graph
+fn load_graph(data: &str) -> Option<(String, BTreeMap<String, NodeData>)> {
+    let nodes = parse_nodes(data)?;
+    let errors = validate::validate(&nodes);
+    if !errors.is_empty() {
+        println!("Refusing to solve an invalid tower:");
+        for error in errors.iter() {
+            println!("  {}", error);
+        }
+        return None;
+    }
+    let g = construct_graph(nodes);
+    let root = find_root(&g)?.to_owned();
+    Some((root, g))
+}
+
+fn print_correction(tower: &tower::Tower) {
+    if tower.is_balanced() {
+        println!("tower is already balanced");
+        return;
+    }
+    match tower.correction() {
+        None => println!("no single weight change balances the tower"),
+        Some((label, val)) => println!("'{}' should weigh {}", label, val),
+    }
+}
+
+// reads edit commands from stdin and applies them to the tower
+fn edit(mut tower: tower::Tower) {
+    let stdin = io::stdin();
+    for line in stdin.lock().lines().map_while(Result::ok) {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["set", label, weight] => {
+                match weight.parse::<i32>() {
+                    Ok(w) => {
+                        if tower.update_weight(label, w) {
+                            println!("tower is {}",
+                                     if tower.is_balanced() { "balanced" } else { "unbalanced" });
+                        } else {
+                            println!("no program named '{}'", label);
+                        }
+                    }
+                    Err(_) => println!("invalid weight '{}'", weight),
+                }
+            }
+            ["show", label] => {
+                match tower.get(label) {
+                    Some(node) => {
+                        println!("{}: weight {}, total {}, {:?}",
+                                 label,
+                                 node.weight,
+                                 node.total_weight,
+                                 node.balance)
+                    }
+                    None => println!("no program named '{}'", label),
+                }
+            }
+            ["balanced"] => println!("{}", tower.is_balanced()),
+            ["fix"] => print_correction(&tower),
+            [] => (),
+            _ => println!("commands: set <label> <weight>, show <label>, balanced, fix"),
+        }
+    }
+}
+
 fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mode = args.first().map(|s| s.as_str());
     let mut data = String::new();
-    if let Ok(_) = io::stdin().read_to_string(&mut data) {
-        if let Some(mut g) = parse_graph(&data) {
-            if let Some(root) = find_root(&g).map(|s| s.to_owned()) {
+    if mode == Some("edit") {
+        // the tower comes from a file so that stdin is free for commands
+        match args.get(1) {
+            Some(path) => {
+                let read = File::open(path).and_then(|mut f| f.read_to_string(&mut data));
+                if let Err(err) = read {
+                    println!("could not read '{}': {}", path, err);
+                    return;
+                }
+            }
+            None => {
+                println!("usage: day07 edit <tower file>");
+                return;
+            }
+        }
+    } else if io::stdin().read_to_string(&mut data).is_err() {
+        return;
+    }
+    if let Some((root, mut g)) = load_graph(&data) {
+        match mode {
+            Some("dot") => {
+                weigh_tree(&root, &mut g);
+                print!("{}", export::to_dot(&root, &g));
+            }
+            Some("json") => {
+                weigh_tree(&root, &mut g);
+                print!("{}", export::to_json(&root, &g));
+            }
+            Some("edit") => {
+                let tower = tower::Tower::new(root, g);
+                println!("loaded tower rooted at '{}'", tower.root());
+                edit(tower);
+            }
+            Some(other) => {
+                println!("unknown mode '{}', expected 'dot', 'json' or 'edit'", other)
+            }
+            None => {
                 println!("root of graph: {}", root);
                 match balance_tree(&root, &mut g) {
                     None => println!("We didn't manage to balance the tree"),
@@ -239,3 +349,28 @@ fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub const EXAMPLE: &str = "pbga (66)\nxhth (57)\nebii (61)\nhavc (66)\nktlj (57)\n\
+                               fwft (72) -> ktlj, cntj, xhth\nqoyq (66)\npadx (45) -> pbga, havc, qoyq\n\
+                               tknk (41) -> ugml, padx, fwft\njptl (61)\nugml (68) -> gyxo, ebii, jptl\n\
+                               gyxo (61)\ncntj (57)\n";
+
+    // the example tower from the puzzle, with total weights and balance filled in
+    pub fn example() -> (String, BTreeMap<String, NodeData>) {
+        let (root, mut g) = load_graph(EXAMPLE).unwrap();
+        weigh_tree(&root, &mut g);
+        (root, g)
+    }
+
+    #[test]
+    fn puzzle_example() {
+        let (root, mut g) = example();
+        assert_eq!(root, "tknk");
+        assert_eq!(g["ugml"].total_weight, 251);
+        assert_eq!(balance_tree(&root, &mut g), Some(("ugml", 60)));
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use super::{balance_tree_helper, weigh_tree, Balance, NodeData};
+
+// bookkeeping that lets a node decide its balance without looking at its children
+#[derive(Debug, Default)]
+struct Counts {
+    // number of children for every total weight among the children
+    child_weights: BTreeMap<i32, usize>,
+    unbalanced_children: usize,
+}
+
+impl Counts {
+    fn balance(&self) -> Balance {
+        if self.child_weights.len() <= 1 && self.unbalanced_children == 0 {
+            Balance::Balanced
+        } else {
+            Balance::Unbalanced
+        }
+    }
+
+    fn replace_weight(&mut self, old: i32, new: i32) {
+        let remove = match self.child_weights.get_mut(&old) {
+            Some(count) => {
+                *count -= 1;
+                *count == 0
+            }
+            None => false,
+        };
+        if remove {
+            self.child_weights.remove(&old);
+        }
+        *self.child_weights.entry(new).or_insert(0) += 1;
+    }
+}
+
+// a tower that keeps total weights and balance up to date while weights are edited
+#[derive(Debug)]
+pub struct Tower {
+    root: String,
+    graph: BTreeMap<String, NodeData>,
+    counts: BTreeMap<String, Counts>,
+}
+
+impl Tower {
+    pub fn new(root: String, mut graph: BTreeMap<String, NodeData>) -> Tower {
+        weigh_tree(&root, &mut graph);
+        let mut counts = BTreeMap::new();
+        for (label, node) in graph.iter() {
+            let mut c = Counts::default();
+            for child in node.children.iter() {
+                if let Some(child_node) = graph.get(child) {
+                    *c.child_weights.entry(child_node.total_weight).or_insert(0) += 1;
+                    if child_node.balance == Balance::Unbalanced {
+                        c.unbalanced_children += 1;
+                    }
+                }
+            }
+            counts.insert(label.clone(), c);
+        }
+        Tower { root, graph, counts }
+    }
+
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    pub fn get(&self, label: &str) -> Option<&NodeData> {
+        self.graph.get(label)
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.graph.get(&self.root).is_none_or(|node| node.balance == Balance::Balanced)
+    }
+
+    // changes the weight of a single program, then walks up through the parents
+    // adjusting total weights and balance, so this is proportional to the depth of the node
+    pub fn update_weight(&mut self, label: &str, weight: i32) -> bool {
+        let delta = match self.graph.get_mut(label) {
+            Some(node) => {
+                let delta = weight - node.weight;
+                node.weight = weight;
+                delta
+            }
+            None => return false,
+        };
+        let mut current = Some(label.to_owned());
+        while let Some(label) = current {
+            let (old_total, old_balance, parent) = {
+                let balance = self.counts[&label].balance();
+                let node = self.graph.get_mut(&label).unwrap();
+                let old = (node.total_weight, node.balance, node.parent.clone());
+                node.total_weight += delta;
+                node.balance = balance;
+                old
+            };
+            if let Some(ref p) = parent {
+                let new_balance = self.graph[&label].balance;
+                let counts = self.counts.get_mut(p).unwrap();
+                counts.replace_weight(old_total, old_total + delta);
+                if old_balance != new_balance {
+                    if new_balance == Balance::Unbalanced {
+                        counts.unbalanced_children += 1;
+                    } else {
+                        counts.unbalanced_children -= 1;
+                    }
+                }
+            }
+            current = parent;
+        }
+        true
+    }
+
+    // the program whose weight should change to balance the tower, and its required weight
+    pub fn correction(&self) -> Option<(&str, i32)> {
+        match self.graph.get(&self.root) {
+            Some(node) if node.balance == Balance::Unbalanced => {
+                balance_tree_helper(node, &self.graph, None)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::example;
+    use super::*;
+
+    // the totals and balance of every program have to match weighing the tower from scratch
+    fn assert_consistent(tower: &Tower) {
+        let mut fresh: BTreeMap<String, NodeData> = tower.graph
+            .iter()
+            .map(|(label, node)| {
+                let mut copy = NodeData::from_weight_children(node.weight, node.children.clone());
+                copy.parent = node.parent.clone();
+                (label.clone(), copy)
+            })
+            .collect();
+        weigh_tree(&tower.root, &mut fresh);
+        for (label, node) in tower.graph.iter() {
+            assert_eq!((node.total_weight, node.balance),
+                       (fresh[label].total_weight, fresh[label].balance),
+                       "{}",
+                       label);
+        }
+    }
+
+    #[test]
+    fn updates_match_weighing_again() {
+        let (root, g) = example();
+        let mut tower = Tower::new(root, g);
+        assert_consistent(&tower);
+        assert_eq!(tower.correction(), Some(("ugml", 60)));
+
+        // the correction expected after each edit; none means the tower is balanced
+        let edits = [("ugml", 60, None),
+                     ("gyxo", 70, Some(("gyxo", 61))),
+                     // two programs are off now, and the first one is named
+                     ("ebii", 70, Some(("gyxo", 61))),
+                     ("jptl", 70, Some(("ugml", 33))),
+                     ("ugml", 33, None),
+                     ("pbga", 0, Some(("pbga", 66))),
+                     ("pbga", 66, None),
+                     ("tknk", 1000, None),
+                     ("cntj", 57, None)];
+        for &(label, weight, correction) in edits.iter() {
+            assert!(tower.update_weight(label, weight));
+            assert_consistent(&tower);
+            assert_eq!(tower.correction(), correction, "after setting {} to {}", label, weight);
+            assert_eq!(tower.is_balanced(), correction.is_none());
+        }
+        assert!(!tower.update_weight("nope", 1));
+    }
+}
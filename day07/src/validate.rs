@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fmt;
+
+use super::RawNode;
+
+#[derive(Debug, PartialEq)]
+pub enum TowerError {
+    DuplicateDefinition(String),
+    DanglingChild { parent: String, child: String },
+    MultipleParents { child: String, parents: Vec<String> },
+    MultipleRoots(Vec<String>),
+    NoRoot,
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for TowerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TowerError::DuplicateDefinition(ref label) => {
+                write!(f, "program '{}' is defined more than once", label)
+            }
+            TowerError::DanglingChild { ref parent, ref child } => {
+                write!(f,
+                       "program '{}' holds '{}', which is never defined",
+                       parent,
+                       child)
+            }
+            TowerError::MultipleParents { ref child, ref parents } => {
+                write!(f,
+                       "program '{}' is held by more than one program: {}",
+                       child,
+                       parents.join(", "))
+            }
+            TowerError::MultipleRoots(ref roots) => {
+                write!(f, "tower has more than one root: {}", roots.join(", "))
+            }
+            TowerError::NoRoot => write!(f, "tower has no root"),
+            TowerError::Cycle(ref path) => {
+                write!(f, "programs form a cycle: {} -> {}", path.join(" -> "), path[0])
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mark {
+    InProgress,
+    Done,
+}
+
+// walks the children of every program depth first, reporting each back edge as a cycle
+fn find_cycles(children: &BTreeMap<&str, &[String]>) -> Vec<TowerError> {
+    let mut errors = Vec::new();
+    let mut marks: BTreeMap<&str, Mark> = BTreeMap::new();
+    for &start in children.keys() {
+        if marks.contains_key(start) {
+            continue;
+        }
+        // stack of programs on the current path, with the index of the next child to visit
+        let mut path: Vec<(&str, usize)> = vec![(start, 0)];
+        marks.insert(start, Mark::InProgress);
+        while let Some(&mut (label, ref mut next)) = path.last_mut() {
+            let kids = children[label];
+            if *next == kids.len() {
+                marks.insert(label, Mark::Done);
+                path.pop();
+                continue;
+            }
+            let child = kids[*next].as_str();
+            *next += 1;
+            if !children.contains_key(child) {
+                // dangling children are reported separately
+                continue;
+            }
+            match marks.get(child).cloned() {
+                None => {
+                    marks.insert(child, Mark::InProgress);
+                    path.push((child, 0));
+                }
+                Some(Mark::InProgress) => {
+                    let begin = path.iter().position(|&(l, _)| l == child).unwrap();
+                    let cycle = path[begin..].iter().map(|&(l, _)| l.to_owned()).collect();
+                    errors.push(TowerError::Cycle(cycle));
+                }
+                Some(Mark::Done) => (),
+            }
+        }
+    }
+    errors
+}
+
+pub fn validate(nodes: &[RawNode]) -> Vec<TowerError> {
+    let mut errors = Vec::new();
+
+    let mut children: BTreeMap<&str, &[String]> = BTreeMap::new();
+    for (label, _, kids) in nodes.iter() {
+        let kids = kids.as_ref().map(|v| v.as_slice()).unwrap_or(&[]);
+        match children.entry(label) {
+            Entry::Vacant(v) => {
+                v.insert(kids);
+            }
+            Entry::Occupied(_) => errors.push(TowerError::DuplicateDefinition(label.clone())),
+        }
+    }
+
+    let mut parents: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (&label, kids) in children.iter() {
+        for child in kids.iter() {
+            if !children.contains_key(child.as_str()) {
+                errors.push(TowerError::DanglingChild {
+                    parent: label.to_owned(),
+                    child: child.clone(),
+                });
+            }
+            parents.entry(child).or_default().push(label.to_owned());
+        }
+    }
+    for (&child, held_by) in parents.iter() {
+        if held_by.len() > 1 {
+            errors.push(TowerError::MultipleParents {
+                child: child.to_owned(),
+                parents: held_by.clone(),
+            });
+        }
+    }
+
+    let roots = children.keys()
+        .filter(|label| !parents.contains_key(*label))
+        .map(|label| label.to_string())
+        .collect::<Vec<_>>();
+    match roots.len() {
+        0 => errors.push(TowerError::NoRoot),
+        1 => (),
+        _ => errors.push(TowerError::MultipleRoots(roots)),
+    }
+
+    errors.extend(find_cycles(&children));
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_nodes;
+    use super::*;
+
+    fn errors(input: &str) -> Vec<TowerError> {
+        validate(&parse_nodes(input).unwrap())
+    }
+
+    fn names(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn example_is_valid() {
+        assert_eq!(errors(super::super::tests::EXAMPLE), vec![]);
+    }
+
+    #[test]
+    fn duplicate_definition() {
+        assert_eq!(errors("a (1) -> b\nb (2)\nb (3)\n"),
+                   vec![TowerError::DuplicateDefinition("b".to_owned())]);
+    }
+
+    #[test]
+    fn dangling_child() {
+        assert_eq!(errors("a (1) -> b, c\nb (2)\n"),
+                   vec![TowerError::DanglingChild { parent: "a".to_owned(), child: "c".to_owned() }]);
+    }
+
+    #[test]
+    fn multiple_parents() {
+        assert_eq!(errors("r (1) -> a, b\na (1) -> c\nb (1) -> c\nc (1)\n"),
+                   vec![TowerError::MultipleParents { child: "c".to_owned(), parents: names(&["a", "b"]) }]);
+    }
+
+    #[test]
+    fn roots() {
+        assert_eq!(errors("a (1) -> b\nb (1)\nc (1)\n"),
+                   vec![TowerError::MultipleRoots(names(&["a", "c"]))]);
+        assert_eq!(errors("a (1) -> b\nb (1) -> c\nc (1) -> a\n"),
+                   vec![TowerError::NoRoot, TowerError::Cycle(names(&["a", "b", "c"]))]);
+    }
+
+    #[test]
+    fn cycle_path() {
+        // the search enters the cycle from x, which is not part of it
+        let found = errors("x (1) -> y\ny (1) -> z\nz (1) -> y\n");
+        assert_eq!(found,
+                   vec![TowerError::MultipleParents { child: "y".to_owned(), parents: names(&["x", "z"]) },
+                        TowerError::Cycle(names(&["y", "z"]))]);
+        assert_eq!(found[1].to_string(), "programs form a cycle: y -> z -> y");
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::{Comparison, Cond, Ins, Operand, Operation};
+
+// the set of values a register might hold, kept in i64 so arithmetic can't overflow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub lo: i64,
+    pub hi: i64,
+}
+
+const TOP: Interval = Interval {
+    lo: i32::MIN as i64,
+    hi: i32::MAX as i64,
+};
+
+impl Interval {
+    fn constant(val: i32) -> Interval {
+        Interval {
+            lo: val as i64,
+            hi: val as i64,
+        }
+    }
+
+    // registers wrap around on overflow, so anything out of range could become anything
+    fn clamp(lo: i64, hi: i64) -> Interval {
+        if lo < TOP.lo || hi > TOP.hi {
+            TOP
+        } else {
+            Interval { lo, hi }
+        }
+    }
+
+    fn join(self, other: Interval) -> Interval {
+        Interval {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.max(other.hi),
+        }
+    }
+
+    fn contains(&self, val: i64) -> bool {
+        self.lo <= val && val <= self.hi
+    }
+
+    fn is_constant(&self) -> bool {
+        self.lo == self.hi
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == TOP {
+            write!(f, "any")
+        } else if self.is_constant() {
+            write!(f, "{}", self.lo)
+        } else {
+            write!(f, "[{}, {}]", self.lo, self.hi)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Truth {
+    Always,
+    Never,
+    Maybe,
+}
+
+impl Truth {
+    fn from_bounds(always: bool, never: bool) -> Truth {
+        if always {
+            Truth::Always
+        } else if never {
+            Truth::Never
+        } else {
+            Truth::Maybe
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Relation {
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+}
+
+impl Relation {
+    fn of(comparison: &Comparison) -> (Relation, &Operand) {
+        match *comparison {
+            Comparison::Eq(ref val) => (Relation::Eq, val),
+            Comparison::Ne(ref val) => (Relation::Ne, val),
+            Comparison::Gt(ref val) => (Relation::Gt, val),
+            Comparison::Lt(ref val) => (Relation::Lt, val),
+            Comparison::Ge(ref val) => (Relation::Ge, val),
+            Comparison::Le(ref val) => (Relation::Le, val),
+        }
+    }
+
+    fn negate(self) -> Relation {
+        match self {
+            Relation::Eq => Relation::Ne,
+            Relation::Ne => Relation::Eq,
+            Relation::Gt => Relation::Le,
+            Relation::Lt => Relation::Ge,
+            Relation::Ge => Relation::Lt,
+            Relation::Le => Relation::Gt,
+        }
+    }
+
+    fn truth(self, a: Interval, b: Interval) -> Truth {
+        match self {
+            Relation::Eq => {
+                Truth::from_bounds(a.is_constant() && a == b, a.hi < b.lo || b.hi < a.lo)
+            }
+            Relation::Ne => {
+                Truth::from_bounds(a.hi < b.lo || b.hi < a.lo, a.is_constant() && a == b)
+            }
+            Relation::Gt => Truth::from_bounds(a.lo > b.hi, a.hi <= b.lo),
+            Relation::Lt => Truth::from_bounds(a.hi < b.lo, a.lo >= b.hi),
+            Relation::Ge => Truth::from_bounds(a.lo >= b.hi, a.hi < b.lo),
+            Relation::Le => Truth::from_bounds(a.hi <= b.lo, a.lo > b.hi),
+        }
+    }
+
+    // the part of `a` for which the relation with some value in `b` can hold
+    fn restrict(self, a: Interval, b: Interval) -> Option<Interval> {
+        let (lo, hi) = match self {
+            Relation::Eq => (a.lo.max(b.lo), a.hi.min(b.hi)),
+            Relation::Ne if b.is_constant() && a.lo == b.lo => (a.lo + 1, a.hi),
+            Relation::Ne if b.is_constant() && a.hi == b.lo => (a.lo, a.hi - 1),
+            Relation::Ne => (a.lo, a.hi),
+            Relation::Gt => (a.lo.max(b.lo + 1), a.hi),
+            Relation::Lt => (a.lo, a.hi.min(b.hi - 1)),
+            Relation::Ge => (a.lo.max(b.lo), a.hi),
+            Relation::Le => (a.lo, a.hi.min(b.hi)),
+        };
+        if lo <= hi {
+            Some(Interval { lo, hi })
+        } else {
+            None
+        }
+    }
+}
+
+// abstract register file, registers that were never written hold `initial`
+#[derive(Debug, Clone)]
+struct State<'a> {
+    regs: BTreeMap<&'a str, Interval>,
+    initial: Interval,
+}
+
+impl<'a> State<'a> {
+    fn get(&self, register: &str) -> Interval {
+        self.regs.get(register).cloned().unwrap_or(self.initial)
+    }
+
+    fn operand(&self, operand: &Operand) -> Interval {
+        match *operand {
+            Operand::Literal(val) => Interval::constant(val),
+            Operand::Register(ref reg) => self.get(reg),
+        }
+    }
+
+    fn truth(&self, cond: &Cond) -> Truth {
+        match *cond {
+            Cond::Compare { ref register, ref comparison } => {
+                let (relation, operand) = Relation::of(comparison);
+                relation.truth(self.get(register), self.operand(operand))
+            }
+            Cond::And(ref a, ref b) => {
+                match (self.truth(a), self.truth(b)) {
+                    (Truth::Never, _) | (_, Truth::Never) => Truth::Never,
+                    (Truth::Always, Truth::Always) => Truth::Always,
+                    _ => Truth::Maybe,
+                }
+            }
+            Cond::Or(ref a, ref b) => {
+                match (self.truth(a), self.truth(b)) {
+                    (Truth::Always, _) | (_, Truth::Always) => Truth::Always,
+                    (Truth::Never, Truth::Never) => Truth::Never,
+                    _ => Truth::Maybe,
+                }
+            }
+            Cond::Not(ref a) => {
+                match self.truth(a) {
+                    Truth::Always => Truth::Never,
+                    Truth::Never => Truth::Always,
+                    Truth::Maybe => Truth::Maybe,
+                }
+            }
+        }
+    }
+
+    // narrows the registers to the values for which `cond` evaluates to `outcome`,
+    // or None if that can't happen
+    fn refine(&self, cond: &'a Cond, outcome: bool) -> Option<State<'a>> {
+        match *cond {
+            Cond::Compare { ref register, ref comparison } => {
+                let (relation, operand) = Relation::of(comparison);
+                let relation = if outcome { relation } else { relation.negate() };
+                let narrowed = relation.restrict(self.get(register), self.operand(operand))?;
+                let mut state = self.clone();
+                state.regs.insert(register, narrowed);
+                Some(state)
+            }
+            Cond::And(ref a, ref b) if outcome => self.refine(a, true)?.refine(b, true),
+            Cond::Or(ref a, ref b) if !outcome => self.refine(a, false)?.refine(b, false),
+            Cond::Not(ref a) => self.refine(a, !outcome),
+            // one of two branches was taken; keeping everything is always safe
+            _ => Some(self.clone()),
+        }
+    }
+}
+
+// the result of an operation, and whether it might divide by zero
+fn operate(operation: &Operation, a: Interval, b: Interval) -> (Interval, bool) {
+    let corners = |f: &dyn Fn(i64, i64) -> i64| {
+        let vals = [f(a.lo, b.lo), f(a.lo, b.hi), f(a.hi, b.lo), f(a.hi, b.hi)];
+        Interval::clamp(*vals.iter().min().unwrap(), *vals.iter().max().unwrap())
+    };
+    match *operation {
+        Operation::Inc(_) => (Interval::clamp(a.lo + b.lo, a.hi + b.hi), false),
+        Operation::Dec(_) => (Interval::clamp(a.lo - b.hi, a.hi - b.lo), false),
+        Operation::Mul(_) => (corners(&|x, y| x * y), false),
+        Operation::Set(_) => (b, false),
+        Operation::Div(_) => {
+            if b.contains(0) {
+                // the magnitude can only shrink, except for dividing by a negative number
+                let m = a.lo.abs().max(a.hi.abs());
+                (Interval::clamp(-m, m), true)
+            } else {
+                (corners(&|x, y| x / y), false)
+            }
+        }
+        Operation::Mod(_) => {
+            // the result takes the sign of `a` and is smaller than the largest `|b|`
+            let m = (b.lo.abs().max(b.hi.abs()) - 1).max(0);
+            let lo = if a.lo < 0 { a.lo.max(-m) } else { 0 };
+            let hi = if a.hi > 0 { a.hi.min(m) } else { 0 };
+            (Interval { lo, hi }, b.contains(0))
+        }
+    }
+}
+
+fn operand(operation: &Operation) -> &Operand {
+    match *operation {
+        Operation::Inc(ref val) |
+        Operation::Dec(ref val) |
+        Operation::Mul(ref val) |
+        Operation::Div(ref val) |
+        Operation::Mod(ref val) |
+        Operation::Set(ref val) => val,
+    }
+}
+
+fn cond_reads<'a>(cond: &'a Cond, reads: &mut Vec<&'a str>) {
+    match *cond {
+        Cond::Compare { ref register, ref comparison } => {
+            reads.push(register);
+            if let Operand::Register(ref reg) = *Relation::of(comparison).1 {
+                reads.push(reg);
+            }
+        }
+        Cond::And(ref a, ref b) | Cond::Or(ref a, ref b) => {
+            cond_reads(a, reads);
+            cond_reads(b, reads);
+        }
+        Cond::Not(ref a) => cond_reads(a, reads),
+    }
+}
+
+pub struct Analysis<'a> {
+    pub warnings: Vec<(usize, String)>,
+    // possible values once the script has finished
+    pub final_ranges: BTreeMap<&'a str, Interval>,
+    // every value a register might hold at some point
+    pub lifetime_ranges: BTreeMap<&'a str, Interval>,
+}
+
+// the interpreter starts every register at 0, but a script can also be checked
+// for any starting values by passing `any_start`
+pub fn analyze(script: &[(Ins, Cond)], any_start: bool) -> Analysis<'_> {
+    let initial = if any_start { TOP } else { Interval::constant(0) };
+    let mut warnings = Vec::new();
+    let mut state = State {
+        regs: BTreeMap::new(),
+        initial,
+    };
+    let mut lifetime: BTreeMap<&str, Interval> = BTreeMap::new();
+    let mut reads: Vec<&str> = Vec::new();
+    let mut first_write: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for (ins, cond) in script.iter() {
+        cond_reads(cond, &mut reads);
+        if let Operand::Register(ref reg) = *operand(&ins.operation) {
+            reads.push(reg);
+        }
+        first_write.entry(&ins.register).or_insert(ins.line);
+
+        let passing = match state.truth(cond) {
+            Truth::Never => None,
+            _ => state.refine(cond, true),
+        };
+        let passing = match passing {
+            Some(passing) => passing,
+            None => {
+                warnings.push((ins.line, format!("condition `{}` can never pass", cond)));
+                continue;
+            }
+        };
+        let always = state.truth(cond) == Truth::Always || state.refine(cond, false).is_none();
+        if always {
+            warnings.push((ins.line, format!("condition `{}` always passes", cond)));
+        }
+
+        let val = passing.operand(operand(&ins.operation));
+        let (result, divides_by_zero) = operate(&ins.operation, passing.get(&ins.register), val);
+        if divides_by_zero {
+            let what = if val.is_constant() { "divides" } else { "might divide" };
+            warnings.push((ins.line, format!("`{}` {} by zero", ins, what)));
+        }
+        let result = if always {
+            result
+        } else {
+            result.join(state.get(&ins.register))
+        };
+        state.regs.insert(&ins.register, result);
+        let seen = lifetime.entry(&ins.register).or_insert(initial);
+        *seen = seen.join(result);
+    }
+
+    for (reg, line) in first_write {
+        if !reads.contains(&reg) {
+            warnings.push((line, format!("register '{}' is written but never read", reg)));
+        }
+    }
+    warnings.sort_by_key(|&(line, _)| line);
+
+    Analysis {
+        warnings,
+        final_ranges: state.regs,
+        lifetime_ranges: lifetime,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_script;
+    use super::*;
+
+    fn warnings(script: &str, any_start: bool) -> Vec<(usize, String)> {
+        analyze(&parse_script(script).unwrap(), any_start).warnings
+    }
+
+    fn owned(expected: &[(usize, &str)]) -> Vec<(usize, String)> {
+        expected.iter().map(|&(line, warning)| (line, warning.to_owned())).collect()
+    }
+
+    fn show(ranges: &BTreeMap<&str, Interval>) -> Vec<(String, String)> {
+        ranges.iter().map(|(reg, range)| (reg.to_string(), range.to_string())).collect()
+    }
+
+    #[test]
+    fn decided_conditions_and_unread_registers() {
+        let found = warnings("a inc 1 if b > 0\n\
+                              a inc 1 if b == 0\n\
+                              b inc a if a > 0\n\
+                              c inc 1 if a > 0 and b < 5\n",
+                             false);
+        assert_eq!(found,
+                   owned(&[(1, "condition `b > 0` can never pass"),
+                           (2, "condition `b == 0` always passes"),
+                           (3, "condition `a > 0` always passes"),
+                           (4, "condition `a > 0 and b < 5` always passes"),
+                           (4, "register 'c' is written but never read")]));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let script = "a mod b if b < 10\nc inc 1 if a > 5\n";
+        assert_eq!(warnings(script, false),
+                   owned(&[(1, "condition `b < 10` always passes"),
+                           (1, "`a mod b` divides by zero"),
+                           (2, "condition `a > 5` can never pass"),
+                           (2, "register 'c' is written but never read")]));
+
+        // from any start, b only might be zero and neither condition is decided
+        assert_eq!(warnings(script, true),
+                   owned(&[(1, "`a mod b` might divide by zero"),
+                           (2, "register 'c' is written but never read")]));
+    }
+
+    #[test]
+    fn ranges() {
+        let script = parse_script("a inc 7 if b == 0\n\
+                                   c set a if a > 0\n\
+                                   c div b if c > 0\n\
+                                   d inc 1 if c > 3\n")
+            .unwrap();
+        let analysis = analyze(&script, false);
+        let strings = |pairs: &[(&str, &str)]| {
+            pairs.iter().map(|&(reg, range)| (reg.to_owned(), range.to_owned())).collect::<Vec<_>>()
+        };
+        assert_eq!(show(&analysis.final_ranges), strings(&[("a", "7"), ("c", "[-7, 7]"), ("d", "[0, 1]")]));
+        assert_eq!(show(&analysis.lifetime_ranges),
+                   strings(&[("a", "[0, 7]"), ("c", "[-7, 7]"), ("d", "[0, 1]")]));
+
+        // nothing is known about registers that may start anywhere
+        let analysis = analyze(&script, true);
+        assert_eq!(show(&analysis.final_ranges), strings(&[("a", "any"), ("c", "any"), ("d", "any")]));
+    }
+}
@@ -0,0 +1,258 @@
+use std::cmp;
+use std::collections::HashMap;
+
+use super::{parse_lines, Comparison, Cond, Ins, Operand, Operation};
+
+#[derive(Debug, Clone, Copy)]
+enum Arg {
+    Literal(i32),
+    Register(u32),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Test {
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Arith {
+    Inc,
+    Dec,
+    Mul,
+    Div,
+    Mod,
+    Set,
+}
+
+// conditions set a single flag, which the jumps look at
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Test(Test, u32, Arg),
+    Not,
+    JumpIfFalse(u32),
+    JumpIfTrue(u32),
+    Apply(Arith, u32, Arg),
+}
+
+#[derive(Debug, Default)]
+pub struct Program {
+    ops: Vec<Op>,
+    // script line of every op, only needed for error messages
+    lines: Vec<u32>,
+    names: Vec<String>,
+    indices: HashMap<String, u32>,
+}
+
+impl Program {
+    fn intern(&mut self, name: &str) -> u32 {
+        if let Some(&index) = self.indices.get(name) {
+            return index;
+        }
+        let index = self.names.len() as u32;
+        self.names.push(name.to_owned());
+        self.indices.insert(name.to_owned(), index);
+        index
+    }
+
+    fn arg(&mut self, operand: &Operand) -> Arg {
+        match *operand {
+            Operand::Literal(val) => Arg::Literal(val),
+            Operand::Register(ref reg) => Arg::Register(self.intern(reg)),
+        }
+    }
+
+    fn emit(&mut self, op: Op, line: usize) -> usize {
+        self.ops.push(op);
+        self.lines.push(line as u32);
+        self.ops.len() - 1
+    }
+
+    fn patch(&mut self, at: usize) {
+        let target = self.ops.len() as u32;
+        match self.ops[at] {
+            Op::JumpIfFalse(ref mut t) | Op::JumpIfTrue(ref mut t) => *t = target,
+            _ => unreachable!("only jumps are patched"),
+        }
+    }
+
+    fn compile_cond(&mut self, cond: &Cond, line: usize) {
+        match *cond {
+            Cond::Compare { ref register, ref comparison } => {
+                let (test, operand) = match *comparison {
+                    Comparison::Eq(ref val) => (Test::Eq, val),
+                    Comparison::Ne(ref val) => (Test::Ne, val),
+                    Comparison::Gt(ref val) => (Test::Gt, val),
+                    Comparison::Lt(ref val) => (Test::Lt, val),
+                    Comparison::Ge(ref val) => (Test::Ge, val),
+                    Comparison::Le(ref val) => (Test::Le, val),
+                };
+                let reg = self.intern(register);
+                let arg = self.arg(operand);
+                self.emit(Op::Test(test, reg, arg), line);
+            }
+            // the second half is skipped when the first decides the outcome,
+            // just like the interpreter short circuits
+            Cond::And(ref a, ref b) => {
+                self.compile_cond(a, line);
+                let jump = self.emit(Op::JumpIfFalse(0), line);
+                self.compile_cond(b, line);
+                self.patch(jump);
+            }
+            Cond::Or(ref a, ref b) => {
+                self.compile_cond(a, line);
+                let jump = self.emit(Op::JumpIfTrue(0), line);
+                self.compile_cond(b, line);
+                self.patch(jump);
+            }
+            Cond::Not(ref a) => {
+                self.compile_cond(a, line);
+                self.emit(Op::Not, line);
+            }
+        }
+    }
+
+    pub fn add(&mut self, ins: &Ins, cond: &Cond) {
+        self.compile_cond(cond, ins.line);
+        let skip = self.emit(Op::JumpIfFalse(0), ins.line);
+        let (arith, operand) = match ins.operation {
+            Operation::Inc(ref val) => (Arith::Inc, val),
+            Operation::Dec(ref val) => (Arith::Dec, val),
+            Operation::Mul(ref val) => (Arith::Mul, val),
+            Operation::Div(ref val) => (Arith::Div, val),
+            Operation::Mod(ref val) => (Arith::Mod, val),
+            Operation::Set(ref val) => (Arith::Set, val),
+        };
+        let reg = self.intern(&ins.register);
+        let arg = self.arg(operand);
+        self.emit(Op::Apply(arith, reg, arg), ins.line);
+        self.patch(skip);
+    }
+}
+
+// compiles each line as soon as it is parsed, so the syntax tree of a large script
+// never has to be held in memory all at once
+pub fn compile(script: &str) -> Option<Program> {
+    let mut program = Program::default();
+    if parse_lines(script, |ins, cond| program.add(&ins, &cond)) {
+        Some(program)
+    } else {
+        None
+    }
+}
+
+// the same observable behaviour as `Registers`: reading a register brings it into
+// existence and every value read or written counts towards the maximum
+pub struct Machine {
+    values: Vec<i32>,
+    exists: Vec<bool>,
+    pub maxval: Option<i32>,
+}
+
+impl Machine {
+    pub fn new(program: &Program) -> Machine {
+        Machine {
+            values: vec![0; program.names.len()],
+            exists: vec![false; program.names.len()],
+            maxval: None,
+        }
+    }
+
+    #[inline]
+    fn read(&mut self, reg: u32) -> i32 {
+        let reg = reg as usize;
+        self.exists[reg] = true;
+        let val = self.values[reg];
+        self.maxval = Some(self.maxval.map_or(val, |m| cmp::max(m, val)));
+        val
+    }
+
+    #[inline]
+    fn arg(&mut self, arg: Arg) -> i32 {
+        match arg {
+            Arg::Literal(val) => val,
+            Arg::Register(reg) => self.read(reg),
+        }
+    }
+
+    pub fn run(&mut self, program: &Program) -> Result<(), String> {
+        let ops = &program.ops;
+        let mut pc = 0;
+        let mut flag = false;
+        while pc < ops.len() {
+            match ops[pc] {
+                Op::Test(test, reg, arg) => {
+                    let a = self.read(reg);
+                    let b = self.arg(arg);
+                    flag = match test {
+                        Test::Eq => a == b,
+                        Test::Ne => a != b,
+                        Test::Gt => a > b,
+                        Test::Lt => a < b,
+                        Test::Ge => a >= b,
+                        Test::Le => a <= b,
+                    };
+                }
+                Op::Not => flag = !flag,
+                Op::JumpIfFalse(target) => {
+                    if !flag {
+                        pc = target as usize;
+                        continue;
+                    }
+                }
+                Op::JumpIfTrue(target) => {
+                    if flag {
+                        pc = target as usize;
+                        continue;
+                    }
+                }
+                Op::Apply(arith, reg, arg) => {
+                    let current = self.read(reg);
+                    let val = self.arg(arg);
+                    let new = match arith {
+                        Arith::Inc => current.wrapping_add(val),
+                        Arith::Dec => current.wrapping_sub(val),
+                        Arith::Mul => current.wrapping_mul(val),
+                        Arith::Div if val == 0 => {
+                            return Err(format!("line {}: division by zero", program.lines[pc]))
+                        }
+                        Arith::Div => current.wrapping_div(val),
+                        Arith::Mod if val == 0 => {
+                            return Err(format!("line {}: modulo by zero", program.lines[pc]))
+                        }
+                        Arith::Mod => current.wrapping_rem(val),
+                        Arith::Set => val,
+                    };
+                    self.values[reg as usize] = new;
+                    self.maxval = Some(self.maxval.map_or(new, |m| cmp::max(m, new)));
+                }
+            }
+            pc += 1;
+        }
+        Ok(())
+    }
+
+    pub fn largest_value(&self) -> Option<i32> {
+        self.values
+            .iter()
+            .zip(self.exists.iter())
+            .filter(|&(_, &exists)| exists)
+            .map(|(&val, _)| val)
+            .max()
+    }
+
+    // register values by name, for comparing against the interpreter
+    #[cfg(test)]
+    pub fn registers<'a>(&self, program: &'a Program) -> std::collections::BTreeMap<&'a str, i32> {
+        program.names
+            .iter()
+            .zip(self.values.iter().zip(self.exists.iter()))
+            .filter(|&(_, (_, &exists))| exists)
+            .map(|(name, (&val, _))| (name.as_str(), val))
+            .collect()
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufRead, Read, Write};
+
+use super::{parse_condition, parse_script, Cond, Ins, Operand, Operation, Registers};
+
+const HELP: &str = "commands:
+  load <file>          load a script and start over
+  step [n]             run the next n lines (default 1)
+  run                  run until a breakpoint or the end of the script
+  break <line>         stop before running the given line
+  break if <cond>      stop as soon as the condition holds, e.g. `break if a > 10`
+  breaks               list breakpoints
+  delete <n>           remove breakpoint n
+  print [reg ...]      show all or some registers
+  set <reg> <value>    change a register
+  list                 show the lines around the current one
+  reset                start the script over, keeping breakpoints
+  quit";
+
+enum Breakpoint {
+    Line(usize),
+    When(Cond),
+}
+
+enum Exit {
+    Load(String),
+    Quit,
+}
+
+// collects the comparisons that made `cond` evaluate differently from `want`
+fn explain(registers: &Registers, cond: &Cond, want: bool, out: &mut Vec<String>) {
+    match *cond {
+        Cond::Compare { ref register, ref comparison } => {
+            if registers.test(cond) != want {
+                let mut values = format!("{} = {}", register, registers.get(register));
+                if let Operand::Register(ref other) = *comparison.symbol_and_operand().1 {
+                    values.push_str(&format!(", {} = {}", other, registers.get(other)));
+                }
+                out.push(format!("`{}` is {} ({})", cond, !want, values));
+            }
+        }
+        Cond::And(ref a, ref b) | Cond::Or(ref a, ref b) => {
+            for c in [a, b].iter() {
+                if registers.test(c) != want {
+                    explain(registers, c, want, out);
+                }
+            }
+        }
+        Cond::Not(ref a) => explain(registers, a, !want, out),
+    }
+}
+
+// every register name used by the script, so that `set` can borrow one for the whole session
+fn register_names(script: &[(Ins, Cond)]) -> BTreeSet<&str> {
+    fn cond_names<'a>(cond: &'a Cond, names: &mut BTreeSet<&'a str>) {
+        match *cond {
+            Cond::Compare { ref register, ref comparison } => {
+                names.insert(register);
+                if let Operand::Register(ref reg) = *comparison.symbol_and_operand().1 {
+                    names.insert(reg);
+                }
+            }
+            Cond::And(ref a, ref b) | Cond::Or(ref a, ref b) => {
+                cond_names(a, names);
+                cond_names(b, names);
+            }
+            Cond::Not(ref a) => cond_names(a, names),
+        }
+    }
+    let mut names = BTreeSet::new();
+    for (ins, cond) in script.iter() {
+        names.insert(ins.register.as_str());
+        match ins.operation {
+            Operation::Inc(Operand::Register(ref reg)) |
+            Operation::Dec(Operand::Register(ref reg)) |
+            Operation::Mul(Operand::Register(ref reg)) |
+            Operation::Div(Operand::Register(ref reg)) |
+            Operation::Mod(Operand::Register(ref reg)) |
+            Operation::Set(Operand::Register(ref reg)) => {
+                names.insert(reg.as_str());
+            }
+            _ => (),
+        }
+        cond_names(cond, &mut names);
+    }
+    names
+}
+
+struct Session<'a> {
+    script: &'a [(Ins, Cond)],
+    names: BTreeSet<&'a str>,
+    registers: Registers<'a>,
+    // index of the next instruction to run
+    pc: usize,
+    breakpoints: Vec<Breakpoint>,
+    // the instruction and breakpoint `run` last stopped at, so running again moves on
+    stopped: Option<(usize, usize)>,
+}
+
+impl<'a> Session<'a> {
+    fn new(script: &'a [(Ins, Cond)]) -> Session<'a> {
+        Session {
+            script,
+            names: register_names(script),
+            registers: Registers::new(),
+            pc: 0,
+            breakpoints: Vec::new(),
+            stopped: None,
+        }
+    }
+
+    fn print_line(&self, index: usize) {
+        let (ref ins, ref cond) = self.script[index];
+        let marker = if index == self.pc { "=>" } else { "  " };
+        println!("{} {:>5}: {} if {}", marker, ins.line, ins, cond);
+    }
+
+    // runs one instruction, returning false if the script could not continue
+    fn step(&mut self) -> bool {
+        let (ref ins, ref cond) = match self.script.get(self.pc) {
+            Some(line) => line,
+            None => {
+                println!("end of script");
+                return false;
+            }
+        };
+        match self.registers.apply(ins, cond) {
+            Ok(true) => {
+                println!("line {}: {} -> {} = {}",
+                         ins.line,
+                         ins,
+                         ins.register,
+                         self.registers.get(&ins.register));
+            }
+            Ok(false) => {
+                let mut reasons = Vec::new();
+                explain(&self.registers, cond, true, &mut reasons);
+                println!("line {}: {} skipped, {}", ins.line, ins, reasons.join(", "));
+            }
+            Err(err) => {
+                println!("Error: {}", err);
+                return false;
+            }
+        }
+        self.pc += 1;
+        true
+    }
+
+    // the breakpoint that should stop execution before the next instruction, if any.
+    // when still stopped before it, only the breakpoints after the one that stopped count
+    fn hit_breakpoint(&self) -> Option<usize> {
+        let line = self.script.get(self.pc).map(|(ins, _)| ins.line);
+        let first = match self.stopped {
+            Some((pc, i)) if pc == self.pc => i + 1,
+            _ => 0,
+        };
+        self.breakpoints.iter().skip(first).position(|b| {
+            match *b {
+                Breakpoint::Line(l) => Some(l) == line,
+                Breakpoint::When(ref cond) => self.registers.test(cond),
+            }
+        }).map(|i| first + i)
+    }
+
+    fn run(&mut self) {
+        loop {
+            if let Some(i) = self.hit_breakpoint() {
+                println!("stopped at breakpoint {}", i + 1);
+                if self.pc < self.script.len() {
+                    self.print_line(self.pc);
+                }
+                self.stopped = Some((self.pc, i));
+                return;
+            }
+            if !self.step() {
+                return;
+            }
+        }
+    }
+
+    fn add_breakpoint(&mut self, args: &str) {
+        let breakpoint = if let Some(cond) = args.strip_prefix("if ") {
+            match parse_condition(cond) {
+                Ok(cond) => Breakpoint::When(cond),
+                Err(err) => {
+                    println!("invalid condition: {}", err);
+                    return;
+                }
+            }
+        } else {
+            match args.parse::<usize>() {
+                Ok(line) => Breakpoint::Line(line),
+                Err(_) => {
+                    println!("usage: break <line> | break if <cond>");
+                    return;
+                }
+            }
+        };
+        self.breakpoints.push(breakpoint);
+        println!("breakpoint {} set", self.breakpoints.len());
+    }
+
+    fn print_breakpoints(&self) {
+        for (i, b) in self.breakpoints.iter().enumerate() {
+            match *b {
+                Breakpoint::Line(line) => println!("{}: line {}", i + 1, line),
+                Breakpoint::When(ref cond) => println!("{}: if {}", i + 1, cond),
+            }
+        }
+    }
+
+    fn print_registers(&self, names: &[&str]) {
+        if names.is_empty() {
+            for (reg, val) in self.registers.regs.iter() {
+                println!("{} = {}", reg, val);
+            }
+        } else {
+            for reg in names.iter() {
+                println!("{} = {}", reg, self.registers.get(reg));
+            }
+        }
+    }
+
+    fn set_register(&mut self, reg: &str, value: &str) {
+        let name = match self.names.get(reg) {
+            Some(&name) => name,
+            None => {
+                println!("register '{}' is not used by the script", reg);
+                return;
+            }
+        };
+        match value.parse::<i32>() {
+            Ok(val) => {
+                self.registers.regs.insert(name, val);
+            }
+            Err(_) => println!("invalid value '{}'", value),
+        }
+    }
+
+    fn list(&self) {
+        let start = self.pc.saturating_sub(3);
+        let end = (self.pc + 4).min(self.script.len());
+        for i in start..end {
+            self.print_line(i);
+        }
+        if self.pc == self.script.len() {
+            println!("=> end of script");
+        }
+    }
+
+    fn interact<I: Iterator<Item = String>>(&mut self, commands: &mut I) -> Exit {
+        loop {
+            print!("(day08) ");
+            io::stdout().flush().unwrap();
+            let command = match commands.next() {
+                Some(command) => command,
+                None => return Exit::Quit,
+            };
+            let command = command.trim();
+            let (word, args) = match command.find(' ') {
+                Some(i) => (&command[..i], command[i + 1..].trim()),
+                None => (command, ""),
+            };
+            let words = args.split_whitespace().collect::<Vec<_>>();
+            match word {
+                "" => (),
+                "help" => println!("{}", HELP),
+                "load" if !args.is_empty() => return Exit::Load(args.to_owned()),
+                "step" => {
+                    let count = words.first().and_then(|n| n.parse::<usize>().ok()).unwrap_or(1);
+                    for _ in 0..count {
+                        if !self.step() {
+                            break;
+                        }
+                    }
+                }
+                "run" => self.run(),
+                "break" => self.add_breakpoint(args),
+                "breaks" => self.print_breakpoints(),
+                "delete" => {
+                    match words.first().and_then(|n| n.parse::<usize>().ok()) {
+                        Some(n) if n >= 1 && n <= self.breakpoints.len() => {
+                            self.breakpoints.remove(n - 1);
+                            self.stopped = match self.stopped {
+                                Some((pc, i)) if i >= n - 1 => (i > 0).then(|| (pc, i - 1)),
+                                stopped => stopped,
+                            };
+                        }
+                        _ => println!("no such breakpoint"),
+                    }
+                }
+                "print" => self.print_registers(&words),
+                "set" if words.len() == 2 => self.set_register(words[0], words[1]),
+                "list" => self.list(),
+                "reset" => {
+                    self.registers = Registers::new();
+                    self.pc = 0;
+                    self.stopped = None;
+                }
+                "quit" => return Exit::Quit,
+                _ => println!("unknown command '{}', try `help`", command),
+            }
+        }
+    }
+}
+
+fn load(path: &str) -> Vec<(Ins, Cond)> {
+    let mut script = String::new();
+    if let Err(err) = File::open(path).and_then(|mut f| f.read_to_string(&mut script)) {
+        println!("could not read '{}': {}", path, err);
+        return Vec::new();
+    }
+    match parse_script(&script) {
+        Some(instructions) => {
+            println!("loaded {} lines from '{}'", instructions.len(), path);
+            instructions
+        }
+        None => Vec::new(),
+    }
+}
+
+// the script is loaded from a file, commands are read from stdin
+pub fn repl(path: Option<String>) {
+    let stdin = io::stdin();
+    let mut commands = stdin.lock().lines().map_while(Result::ok);
+    let mut script = path.map(|p| load(&p)).unwrap_or_default();
+    loop {
+        let exit = Session::new(&script).interact(&mut commands);
+        match exit {
+            Exit::Load(path) => script = load(&path),
+            Exit::Quit => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "a inc 1 if b == 0\nb inc 2 if a > 0\nc dec 1 if a == 1\n";
+
+    #[test]
+    fn breakpoints_stop_before_the_line() {
+        let script = parse_script(SCRIPT).unwrap();
+        let mut session = Session::new(&script);
+        session.add_breakpoint("1");
+        session.add_breakpoint("if a == 0");
+        session.add_breakpoint("3");
+        session.run();
+        assert_eq!((session.pc, session.stopped), (0, Some((0, 0))));
+        session.run();
+        assert_eq!((session.pc, session.stopped), (0, Some((0, 1))));
+        session.run();
+        assert_eq!((session.pc, session.stopped), (2, Some((2, 2))));
+        session.run();
+        assert_eq!(session.pc, 3);
+    }
+}
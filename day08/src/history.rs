@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// a single write to a register
+#[derive(Debug)]
+pub struct Change<'a> {
+    pub step: usize,
+    pub line: usize,
+    pub register: &'a str,
+    pub old: i32,
+    pub new: i32,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConditionCount {
+    pub passed: u32,
+    pub failed: u32,
+}
+
+#[derive(Debug)]
+pub struct RegisterStats {
+    pub last: i32,
+    pub min: i32,
+    pub max: i32,
+    pub writes: u32,
+}
+
+// everything that happened while running a script, in order
+#[derive(Debug, Default)]
+pub struct History<'a> {
+    pub changes: Vec<Change<'a>>,
+    // keyed by line in the script
+    pub conditions: BTreeMap<usize, ConditionCount>,
+}
+
+impl<'a> History<'a> {
+    pub fn record_condition(&mut self, line: usize, passed: bool) {
+        let count = self.conditions.entry(line).or_default();
+        if passed {
+            count.passed += 1;
+        } else {
+            count.failed += 1;
+        }
+    }
+
+    // the value a register held once the given number of steps had run
+    pub fn value_at(&self, register: &str, step: usize) -> i32 {
+        self.changes
+            .iter()
+            .take_while(|c| c.step <= step)
+            .filter(|c| c.register == register)
+            .last()
+            .map_or(0, |c| c.new)
+    }
+
+    // values of every written register once the given number of steps had run
+    pub fn registers_at(&self, step: usize) -> BTreeMap<&'a str, i32> {
+        let mut result = BTreeMap::new();
+        for change in self.changes.iter().take_while(|c| c.step <= step) {
+            result.insert(change.register, change.new);
+        }
+        result
+    }
+
+    // registers start at 0, so that counts towards the minimum and maximum
+    pub fn register_stats(&self) -> BTreeMap<&'a str, RegisterStats> {
+        let mut result: BTreeMap<&'a str, RegisterStats> = BTreeMap::new();
+        for change in self.changes.iter() {
+            let stats = result.entry(change.register).or_insert(RegisterStats {
+                last: change.old,
+                min: change.old,
+                max: change.old,
+                writes: 0,
+            });
+            stats.last = change.new;
+            stats.min = stats.min.min(change.new);
+            stats.max = stats.max.max(change.new);
+            stats.writes += 1;
+        }
+        result
+    }
+
+    // the first write of the largest value ever written
+    pub fn global_max(&self) -> Option<&Change<'a>> {
+        self.changes.iter().fold(None, |best: Option<&Change<'a>>, c| {
+            match best {
+                Some(b) if b.new >= c.new => Some(b),
+                _ => Some(c),
+            }
+        })
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "{:<10} {:>12} {:>12} {:>12} {:>8}", "register", "final", "min", "max", "writes")
+            .unwrap();
+        for (reg, stats) in self.register_stats() {
+            writeln!(out,
+                     "{:<10} {:>12} {:>12} {:>12} {:>8}",
+                     reg,
+                     stats.last,
+                     stats.min,
+                     stats.max,
+                     stats.writes)
+                .unwrap();
+        }
+        if let Some(c) = self.global_max() {
+            writeln!(out,
+                     "\nlargest value {} written to {} at step {} (line {})",
+                     c.new,
+                     c.register,
+                     c.step,
+                     c.line)
+                .unwrap();
+        }
+        writeln!(out, "\n{:>6} {:>8} {:>8}", "line", "passed", "failed").unwrap();
+        for (line, count) in self.conditions.iter() {
+            writeln!(out, "{:>6} {:>8} {:>8}", line, count.passed, count.failed).unwrap();
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "{{").unwrap();
+        writeln!(out, "  \"registers\": {{").unwrap();
+        let stats = self.register_stats();
+        for (i, (reg, s)) in stats.iter().enumerate() {
+            writeln!(out,
+                     "    \"{}\": {{\"final\": {}, \"min\": {}, \"max\": {}, \"writes\": {}}}{}",
+                     reg,
+                     s.last,
+                     s.min,
+                     s.max,
+                     s.writes,
+                     if i + 1 < stats.len() { "," } else { "" })
+                .unwrap();
+        }
+        writeln!(out, "  }},").unwrap();
+        match self.global_max() {
+            Some(c) => {
+                writeln!(out,
+                         "  \"global_max\": {{\"value\": {}, \"register\": \"{}\", \"step\": {}, \
+                          \"line\": {}}},",
+                         c.new,
+                         c.register,
+                         c.step,
+                         c.line)
+                    .unwrap()
+            }
+            None => writeln!(out, "  \"global_max\": null,").unwrap(),
+        }
+        writeln!(out, "  \"conditions\": [").unwrap();
+        for (i, (line, count)) in self.conditions.iter().enumerate() {
+            writeln!(out,
+                     "    {{\"line\": {}, \"passed\": {}, \"failed\": {}}}{}",
+                     line,
+                     count.passed,
+                     count.failed,
+                     if i + 1 < self.conditions.len() { "," } else { "" })
+                .unwrap();
+        }
+        writeln!(out, "  ]").unwrap();
+        writeln!(out, "}}").unwrap();
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse_script, Registers};
+
+    #[test]
+    fn recorded_run() {
+        let script = parse_script("a inc 5 if b == 0\n\
+                                   b dec 3 if a > 2\n\
+                                   a set b if b < 0\n\
+                                   c inc 10 if a > 0\n\
+                                   a inc 20 if c == 0\n\
+                                   b mul 2 if a > 100\n\
+                                   c dec 1 if a > 10\n\
+                                   b set 17 if c < 0\n")
+            .unwrap();
+        let mut registers = Registers::with_history();
+        for (ins, cond) in script.iter() {
+            registers.apply(ins, cond).unwrap();
+        }
+        let history = registers.history.unwrap();
+
+        assert_eq!(history.value_at("a", 0), 0);
+        assert_eq!(history.value_at("a", 1), 5);
+        assert_eq!(history.value_at("a", 2), 5);
+        assert_eq!(history.value_at("a", 3), -3);
+        assert_eq!(history.value_at("a", 8), 17);
+        assert_eq!(history.value_at("b", 1), 0);
+        assert_eq!(history.value_at("b", 7), -3);
+        assert!(history.registers_at(0).is_empty());
+        assert_eq!(history.registers_at(3).into_iter().collect::<Vec<_>>(), vec![("a", -3), ("b", -3)]);
+
+        let stats = history.register_stats();
+        let summary: Vec<_> = stats.iter().map(|(&reg, s)| (reg, s.last, s.min, s.max, s.writes)).collect();
+        assert_eq!(summary, vec![("a", 17, -3, 17, 3), ("b", 17, -3, 17, 2), ("c", -1, -1, 0, 1)]);
+
+        // b reaches 17 as well, but a got there first
+        let top = history.global_max().unwrap();
+        assert_eq!((top.new, top.register, top.step, top.line), (17, "a", 5, 5));
+
+        let counts: Vec<_> = history.conditions.iter().map(|(&line, c)| (line, c.passed, c.failed)).collect();
+        assert_eq!(counts,
+                   vec![(1, 1, 0), (2, 1, 0), (3, 1, 0), (4, 0, 1), (5, 1, 0), (6, 0, 1), (7, 1, 0), (8, 1, 0)]);
+    }
+}
@@ -1,40 +1,162 @@
 use std::collections::BTreeMap;
 use std::io::{self, Read};
 use std::cmp;
+use std::env;
+use std::fmt;
 
 extern crate combine;
 use combine::*;
-use combine::char::{char, string, spaces, letter, digit};
+use combine::char::{char, string, spaces, space, letter, digit};
+use combine::primitives::SourcePosition;
+
+mod analysis;
+mod bytecode;
+mod debugger;
+mod history;
+
+use history::History;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Literal(i32),
+    Register(String),
+}
 
 enum Operation {
-    Inc(i32),
-    Dec(i32),
+    Inc(Operand),
+    Dec(Operand),
+    Mul(Operand),
+    Div(Operand),
+    Mod(Operand),
+    Set(Operand),
 }
 
 struct Ins {
     register: String,
     operation: Operation,
+    // line in the script this instruction was read from
+    line: usize,
 }
 
 
 enum Comparison {
-    Eq(i32),
-    Ne(i32),
-    Gt(i32),
-    Lt(i32),
-    Ge(i32),
-    Le(i32),
+    Eq(Operand),
+    Ne(Operand),
+    Gt(Operand),
+    Lt(Operand),
+    Ge(Operand),
+    Le(Operand),
 }
 
-struct Cond {
-    register: String,
-    comparison: Comparison,
+enum Cond {
+    Compare {
+        register: String,
+        comparison: Comparison,
+    },
+    And(Box<Cond>, Box<Cond>),
+    Or(Box<Cond>, Box<Cond>),
+    Not(Box<Cond>),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operand::Literal(val) => write!(f, "{}", val),
+            Operand::Register(ref reg) => write!(f, "{}", reg),
+        }
+    }
+}
+
+impl fmt::Display for Ins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, val) = match self.operation {
+            Operation::Inc(ref val) => ("inc", val),
+            Operation::Dec(ref val) => ("dec", val),
+            Operation::Mul(ref val) => ("mul", val),
+            Operation::Div(ref val) => ("div", val),
+            Operation::Mod(ref val) => ("mod", val),
+            Operation::Set(ref val) => ("set", val),
+        };
+        write!(f, "{} {} {}", self.register, name, val)
+    }
+}
+
+impl Comparison {
+    fn symbol_and_operand(&self) -> (&'static str, &Operand) {
+        match *self {
+            Comparison::Eq(ref val) => ("==", val),
+            Comparison::Ne(ref val) => ("!=", val),
+            Comparison::Gt(ref val) => (">", val),
+            Comparison::Lt(ref val) => ("<", val),
+            Comparison::Ge(ref val) => (">=", val),
+            Comparison::Le(ref val) => ("<=", val),
+        }
+    }
+
+    fn holds(&self, lhs: i32, rhs: i32) -> bool {
+        match *self {
+            Comparison::Eq(_) => lhs == rhs,
+            Comparison::Ne(_) => lhs != rhs,
+            Comparison::Gt(_) => lhs > rhs,
+            Comparison::Lt(_) => lhs < rhs,
+            Comparison::Ge(_) => lhs >= rhs,
+            Comparison::Le(_) => lhs <= rhs,
+        }
+    }
+}
+
+impl Cond {
+    // binding strength, used to decide where parentheses are needed when printing
+    fn precedence(&self) -> u8 {
+        match *self {
+            Cond::Or(..) => 0,
+            Cond::And(..) => 1,
+            Cond::Not(_) | Cond::Compare { .. } => 2,
+        }
+    }
+
+    fn fmt_within(&self, f: &mut fmt::Formatter, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "(")?;
+            self.fmt_within(f, 0)?;
+            return write!(f, ")");
+        }
+        match *self {
+            Cond::Compare { ref register, ref comparison } => {
+                let (symbol, val) = comparison.symbol_and_operand();
+                write!(f, "{} {} {}", register, symbol, val)
+            }
+            Cond::And(ref a, ref b) => {
+                a.fmt_within(f, 1)?;
+                write!(f, " and ")?;
+                b.fmt_within(f, 2)
+            }
+            Cond::Or(ref a, ref b) => {
+                a.fmt_within(f, 0)?;
+                write!(f, " or ")?;
+                b.fmt_within(f, 1)
+            }
+            Cond::Not(ref a) => {
+                write!(f, "not ")?;
+                a.fmt_within(f, 2)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Cond {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_within(f, 0)
+    }
 }
 
 #[derive(Debug)]
 struct Registers<'a> {
     regs: BTreeMap<&'a str, i32>,
     maxval: Option<i32>,
+    // number of instructions applied so far
+    steps: usize,
+    history: Option<History<'a>>,
 }
 
 // helper function
@@ -47,29 +169,106 @@ fn max(a: Option<i32>, b: i32) -> Option<i32> {
 
 impl<'a> Registers<'a> {
     fn new() -> Registers<'a> {
-        Registers { regs: BTreeMap::new(), maxval: None}
+        Registers { regs: BTreeMap::new(), maxval: None, steps: 0, history: None}
+    }
+
+    // like new, but keeps a record of every write and condition
+    fn with_history() -> Registers<'a> {
+        Registers { history: Some(History::default()), ..Registers::new() }
+    }
+
+    // reading a register brings it into existence, just like writing it
+    fn read(&mut self, register: &'a str) -> i32 {
+        let val = *self.regs.entry(register).or_insert(0);
+        self.maxval = max(self.maxval, val);
+        val
+    }
+
+    fn value(&mut self, operand: &'a Operand) -> i32 {
+        match *operand {
+            Operand::Literal(val) => val,
+            Operand::Register(ref reg) => self.read(reg),
+        }
+    }
+
+    fn check(&mut self, cond: &'a Cond) -> bool {
+        match *cond {
+            Cond::Compare { ref register, ref comparison } => {
+                let reg = self.read(register);
+                let val = self.value(comparison.symbol_and_operand().1);
+                comparison.holds(reg, val)
+            }
+            Cond::And(ref a, ref b) => self.check(a) && self.check(b),
+            Cond::Or(ref a, ref b) => self.check(a) || self.check(b),
+            Cond::Not(ref a) => !self.check(a),
+        }
     }
 
-    fn apply(&mut self, ins: &'a Ins, cond: &'a Cond) {
-        let condition_passed = {
-            let reg = self.regs.entry(&cond.register).or_insert(0);
-            self.maxval = max(self.maxval, *reg);
-            match cond.comparison {
-                Comparison::Eq(val) => *reg == val,
-                Comparison::Ne(val) => *reg != val,
-                Comparison::Gt(val) => *reg > val,
-                Comparison::Lt(val) => *reg < val,
-                Comparison::Ge(val) => *reg >= val,
-                Comparison::Le(val) => *reg <= val,
+    // returns whether the condition passed, or an error if the operation could not be done
+    fn apply(&mut self, ins: &'a Ins, cond: &'a Cond) -> Result<bool, String> {
+        self.steps += 1;
+        let passed = self.check(cond);
+        if let Some(ref mut history) = self.history {
+            history.record_condition(ins.line, passed);
+        }
+        if !passed {
+            return Ok(false);
+        }
+        let current = self.read(&ins.register);
+        let new = match ins.operation {
+            Operation::Inc(ref val) => current.wrapping_add(self.value(val)),
+            Operation::Dec(ref val) => current.wrapping_sub(self.value(val)),
+            Operation::Mul(ref val) => current.wrapping_mul(self.value(val)),
+            Operation::Div(ref val) => {
+                match self.value(val) {
+                    0 => return Err(format!("line {}: division by zero", ins.line)),
+                    v => current.wrapping_div(v),
+                }
+            }
+            Operation::Mod(ref val) => {
+                match self.value(val) {
+                    0 => return Err(format!("line {}: modulo by zero", ins.line)),
+                    v => current.wrapping_rem(v),
+                }
             }
+            Operation::Set(ref val) => self.value(val),
         };
-        if condition_passed {
-            let reg = self.regs.entry(&ins.register).or_insert(0);
-            match ins.operation {
-                Operation::Inc(val) => *reg += val,
-                Operation::Dec(val) => *reg -= val,
+        self.regs.insert(&ins.register, new);
+        self.maxval = max(self.maxval, new);
+        if let Some(ref mut history) = self.history {
+            history.changes.push(history::Change {
+                step: self.steps,
+                line: ins.line,
+                register: &ins.register,
+                old: current,
+                new,
+            });
+        }
+        Ok(true)
+    }
+
+    // the current value of a register, without bringing it into existence
+    fn get(&self, register: &str) -> i32 {
+        self.regs.get(register).cloned().unwrap_or(0)
+    }
+
+    fn eval(&self, operand: &Operand) -> i32 {
+        match *operand {
+            Operand::Literal(val) => val,
+            Operand::Register(ref reg) => self.get(reg),
+        }
+    }
+
+    // evaluates a condition without side effects
+    fn test(&self, cond: &Cond) -> bool {
+        match *cond {
+            Cond::Compare { ref register, ref comparison } => {
+                comparison.holds(self.get(register),
+                                 self.eval(comparison.symbol_and_operand().1))
             }
-            self.maxval = max(self.maxval, *reg);
+            Cond::And(ref a, ref b) => self.test(a) && self.test(b),
+            Cond::Or(ref a, ref b) => self.test(a) || self.test(b),
+            Cond::Not(ref a) => !self.test(a),
         }
     }
 
@@ -78,78 +277,288 @@ impl<'a> Registers<'a> {
     }
 }
 
-fn parse_script(instructions: &str) -> Option<Vec<(Ins, Cond)>> {
-    let ident = || many1(letter());
-    let num = || {
-        (optional(char('+').or(char('-'))), many1(digit())).map(|(c, s): (_, String)| {
-            let mut val = s.parse::<i32>().unwrap();
-            if let Some('-') = c {
-                val *= -1;
-            }
-            val
-        })
+type Input<'a> = State<&'a str>;
+
+// a parser followed by any amount of whitespace
+fn lex<'a, P>(p: P) -> impl Parser<Input = Input<'a>, Output = P::Output>
+    where P: Parser<Input = Input<'a>>
+{
+    p.skip(spaces())
+}
+
+// keywords have to end in whitespace or an opening parenthesis, so `andy` is still a register
+fn keyword<'a>(word: &'static str) -> impl Parser<Input = Input<'a>, Output = ()> {
+    try(string(word).skip(skip_many1(space()).or(look_ahead(char('(')).map(|_| ()))))
+        .map(|_| ())
+}
+
+// any word can name a register, keywords included: where one stands is
+// always clear from what follows it
+fn register<'a>() -> impl Parser<Input = Input<'a>, Output = String> {
+    lex(many1(letter()))
+}
+
+fn operand<'a>() -> impl Parser<Input = Input<'a>, Output = Operand> {
+    let num = (optional(char('+').or(char('-'))), many1(digit()))
+        .and_then(|(sign, digits): (Option<char>, String)| {
+            let text: String = sign.into_iter().chain(digits.chars()).collect();
+            text.parse::<i32>()
+        });
+    lex(num).map(Operand::Literal).or(register().map(Operand::Register))
+}
+
+fn operation<'a>() -> impl Parser<Input = Input<'a>, Output = Operation> {
+    let op = |name: &'static str, make: fn(Operand) -> Operation| {
+        keyword(name).with(operand()).map(make)
+    };
+    choice([op("inc", Operation::Inc),
+            op("dec", Operation::Dec),
+            op("mul", Operation::Mul),
+            op("div", Operation::Div),
+            op("mod", Operation::Mod),
+            op("set", Operation::Set)])
+}
+
+fn comparator<'a>() -> impl Parser<Input = Input<'a>, Output = fn(Operand) -> Comparison> {
+    let cmp = |symbol: &'static str, make: fn(Operand) -> Comparison| {
+        try(lex(string(symbol))).map(move |_| make)
+    };
+    choice([cmp("==", Comparison::Eq),
+            cmp("!=", Comparison::Ne),
+            cmp(">=", Comparison::Ge),
+            cmp(">", Comparison::Gt),
+            cmp("<=", Comparison::Le),
+            cmp("<", Comparison::Lt)])
+}
+
+// `not` binds tighter than `and`, which binds tighter than `or`
+fn atom<'a>(input: Input<'a>) -> ParseResult<Cond, Input<'a>> {
+    // a register called `not` is only told apart from the operator by the comparison after it
+    let compare = (try((register(), comparator())), operand())
+        .map(|((register, make), val)| Cond::Compare { register, comparison: make(val) });
+    let group = between(lex(char('(')), lex(char(')')), parser(condition));
+    let not = keyword("not").with(parser(atom)).map(|cond| Cond::Not(Box::new(cond)));
+    compare.or(group).or(not).parse_stream(input)
+}
+
+fn condition<'a>(input: Input<'a>) -> ParseResult<Cond, Input<'a>> {
+    let and = keyword("and").map(|_| |a, b| Cond::And(Box::new(a), Box::new(b)));
+    let or = keyword("or").map(|_| |a, b| Cond::Or(Box::new(a), Box::new(b)));
+    chainl1(chainl1(parser(atom), and), or).parse_stream(input)
+}
+
+// errors are reported against `line`, though `text` holds nothing but that line
+fn parse_line(text: &str, line: usize) -> Result<(Ins, Cond), String> {
+    let input = State {
+        position: SourcePosition { line: line as i32, column: 1 },
+        input: text,
     };
+    let ins = (register(), operation())
+        .map(|(register, operation)| Ins { register, operation, line });
+    (spaces().with(ins), keyword("if"), parser(condition), eof())
+        .map(|(ins, _, cond, _)| (ins, cond))
+        .parse(input)
+        .map(|(parsed, _)| parsed)
+        .map_err(|err| err.to_string())
+}
+
+// a condition on its own, as typed at the debugger prompt
+fn parse_condition(text: &str) -> Result<Cond, String> {
+    (spaces().with(parser(condition)), eof())
+        .map(|(cond, _)| cond)
+        .parse(State::new(text))
+        .map(|(cond, _)| cond)
+        .map_err(|err| err.to_string())
+}
+
+// hands every instruction to `add` as soon as its line is parsed, and reports
+// each line that fails; returns whether the whole script was usable
+fn parse_lines<F: FnMut(Ins, Cond)>(script: &str, mut add: F) -> bool {
+    let mut ok = true;
+    let mut empty = true;
+    for (i, text) in script.lines().enumerate() {
+        if text.trim().is_empty() {
+            continue;
+        }
+        match parse_line(text, i + 1) {
+            Ok((ins, cond)) => {
+                add(ins, cond);
+                empty = false;
+            }
+            Err(err) => {
+                println!("Error: {}", err);
+                ok = false;
+            }
+        }
+    }
+    ok && !empty
+}
+
+fn parse_script(instructions: &str) -> Option<Vec<(Ins, Cond)>> {
+    let mut result = Vec::new();
+    if parse_lines(instructions, |ins, cond| result.push((ins, cond))) {
+        Some(result)
+    } else {
+        None
+    }
+}
 
-    let inc = (string("inc").skip(spaces()), num()).map(|(_, n)| Operation::Inc(n));
-    let dec = (string("dec").skip(spaces()), num()).map(|(_, n)| Operation::Dec(n));
-    let opers = inc.or(dec);
-    let oper = (ident().skip(spaces()), opers).map(|(id, op)| {
-        Ins {
-            register: id,
-            operation: op,
-        }
-    });
-
-    let mut eq = (string("==").skip(spaces()), num()).map(|(_, n)| Comparison::Eq(n));
-    let mut ne = (string("!=").skip(spaces()), num()).map(|(_, n)| Comparison::Ne(n));
-    let mut ge = (try(string(">=")).skip(spaces()), num()).map(|(_, n)| Comparison::Ge(n));
-    let mut gt = (string(">").skip(spaces()), num()).map(|(_, n)| Comparison::Gt(n));
-    let mut le = (try(string("<=")).skip(spaces()), num()).map(|(_, n)| Comparison::Le(n));
-    let mut lt = (string("<").skip(spaces()), num()).map(|(_, n)| Comparison::Lt(n));
-    let comps = choice::<[&mut Parser<Input = State<&str>, Output = Comparison>; 6], _>([&mut eq,
-                                                                                         &mut ne,
-                                                                                         &mut ge,
-                                                                                         &mut gt,
-                                                                                         &mut le,
-                                                                                         &mut lt]);
-    let comp = (ident().skip(spaces()), comps).map(|(id, cmp)| {
-        Cond {
-            register: id,
-            comparison: cmp,
-        }
-    });
-    let line = (oper.skip(spaces()), string("if").skip(spaces()), comp.skip(spaces()))
-        .map(|(op, _, cmp)| (op, cmp));
-    let mut lines = many1(line);
-
-    match lines.parse(State::new(instructions)) {
-        Ok((ins, _)) => Some(ins),
-        Err(err) => {
+// runs the script while recording, then reports on what happened
+fn report_history(instructions: &[(Ins, Cond)], args: &[String]) {
+    let mut registers = Registers::with_history();
+    for (ins, cond) in instructions.iter() {
+        if let Err(err) = registers.apply(ins, cond) {
             println!("Error: {}", err);
-            None
+            break;
+        }
+    }
+    let history = registers.history.unwrap();
+    let at = args.iter().position(|a| a == "at");
+    let step = at.and_then(|i| args.get(i + 1)).and_then(|n| n.parse::<usize>().ok());
+    let json = args.iter().any(|a| a == "json");
+    match step {
+        Some(step) => {
+            // any further arguments name the registers to show
+            let wanted = args[at.unwrap() + 2..].iter().filter(|a| *a != "json").collect::<Vec<_>>();
+            let values = if wanted.is_empty() {
+                history.registers_at(step)
+            } else {
+                wanted.iter().map(|reg| (reg.as_str(), history.value_at(reg, step))).collect()
+            };
+            if json {
+                let fields = values.iter()
+                    .map(|(reg, val)| format!("\"{}\": {}", reg, val))
+                    .collect::<Vec<_>>();
+                println!("{{\"step\": {}, \"registers\": {{{}}}}}", step, fields.join(", "));
+            } else {
+                println!("registers after step {}:", step);
+                for (reg, val) in values.iter() {
+                    println!("{:<10} {:>12}", reg, val);
+                }
+            }
         }
+        None if json => print!("{}", history.to_json()),
+        None => print!("{}", history.to_table()),
+    }
+}
+
+fn lint(instructions: &[(Ins, Cond)], any_start: bool) {
+    let analysis = analysis::analyze(instructions, any_start);
+    for (line, warning) in analysis.warnings.iter() {
+        println!("warning: line {}: {}", line, warning);
+    }
+    println!("{} warnings", analysis.warnings.len());
+    println!("\n{:<10} {:>26} {:>26}", "register", "final range", "lifetime range");
+    for (reg, range) in analysis.final_ranges.iter() {
+        println!("{:<10} {:>26} {:>26}",
+                 reg,
+                 range.to_string(),
+                 analysis.lifetime_ranges[reg].to_string());
+    }
+}
+
+fn print_results(largest: Option<i32>, maxval: Option<i32>) {
+    match largest {
+        Some(val) => println!("largest value in registers: {}", val),
+        None => println!("No values in registers"),
+    }
+    match maxval {
+        Some(val) => println!("largest value over script lifetime: {}", val),
+        None => println!("No value use over program lifetime"),
     }
 }
 
 fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mode = args.first().map(|s| s.as_str());
+    if mode == Some("debug") {
+        debugger::repl(args.get(1).cloned());
+        return;
+    }
     let mut stdin = io::stdin();
     let mut script = String::new();
-    if let Ok(_) = stdin.read_to_string(&mut script) {
+    if stdin.read_to_string(&mut script).is_ok() {
+        if mode == Some("fast") {
+            if let Some(program) = bytecode::compile(&script) {
+                let mut machine = bytecode::Machine::new(&program);
+                match machine.run(&program) {
+                    Ok(()) => print_results(machine.largest_value(), machine.maxval),
+                    Err(err) => println!("Error: {}", err),
+                }
+            }
+            return;
+        }
         if let Some(instructions) = parse_script(&script) {
+            if mode == Some("history") {
+                report_history(&instructions, &args[1..]);
+                return;
+            }
+            if mode == Some("lint") {
+                lint(&instructions, args.iter().any(|a| a == "any-start"));
+                return;
+            }
 
             let mut registers = Registers::new();
-            for &(ref ins, ref cond) in instructions.iter() {
-                registers.apply(ins, cond)
+            for (ins, cond) in instructions.iter() {
+                if let Err(err) = registers.apply(ins, cond) {
+                    println!("Error: {}", err);
+                    return;
+                }
             }
-            match registers.largest_value() {
-                Some(val) => println!("largest value in registers: {}", val),
-                None => println!("No values in registers"),
-            }
-            match registers.maxval {
-                Some(val) => println!("largest value over script lifetime: {}", val),
-                None => println!("No value use over program lifetime"),
+            print_results(registers.largest_value(), registers.maxval);
+        }
+
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(script: &str) -> BTreeMap<String, i32> {
+        let instructions = parse_script(script).unwrap();
+        let mut registers = Registers::new();
+        for (ins, cond) in instructions.iter() {
+            registers.apply(ins, cond).unwrap();
+        }
+        registers.regs.iter().map(|(&reg, &val)| (reg.to_owned(), val)).collect()
+    }
+
+    #[test]
+    fn keywords_name_registers() {
+        let registers = run("not inc 1 if or > -1\n\
+                             if inc 2 if not == 1 and and < 5\n\
+                             or dec -1 if not not > 0 or (if >= 2)\n");
+        assert_eq!(registers["not"], 1);
+        assert_eq!(registers["if"], 2);
+        assert_eq!(registers["or"], 1);
+        assert!(parse_line("a inc 1 if a > b or", 1).is_err());
+        assert!(parse_line("a inc 1 if a > b andy > 1", 1).is_err());
+    }
+
+    // runs the script through both the interpreter and the bytecode machine
+    fn check_bytecode(script: &str) {
+        let instructions = parse_script(script).unwrap();
+        let program = bytecode::compile(script).unwrap();
+        let mut registers = Registers::new();
+        let mut expected = Ok(());
+        for (ins, cond) in instructions.iter() {
+            expected = registers.apply(ins, cond).map(|_| ());
+            if expected.is_err() {
+                break;
             }
         }
+        let mut machine = bytecode::Machine::new(&program);
+        assert_eq!(machine.run(&program), expected);
+        assert_eq!(machine.registers(&program), registers.regs);
+        assert_eq!(machine.maxval, registers.maxval);
+    }
 
+    #[test]
+    fn bytecode_matches_interpreter() {
+        check_bytecode(include_str!("../input"));
+        check_bytecode("a inc 5 if b == 0\nb set a if not (a < 3 or c != 0)\nc mul b if b >= 5\n\
+                        a mod 3 if c > 0 and a <= 5\nd div a if a != 0\n");
+        check_bytecode("a inc 1 if a == 0\nb div a if a > 0\nc div b if b == 0\nd inc 1 if c == 0\n");
     }
 }
@@ -0,0 +1,237 @@
+use std::io::{self, Write};
+use std::mem;
+use std::ops::Range;
+
+// a parsed stream, with the byte range each node covers in the input
+#[derive(Debug, PartialEq)]
+pub enum Node {
+    Group {
+        children: Vec<Node>,
+        span: Range<usize>,
+    },
+    Garbage {
+        // everything between the angle brackets, including cancels
+        raw: String,
+        // number of characters cancelled with `!`
+        cancelled: usize,
+        span: Range<usize>,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub message: String,
+}
+
+fn error<T>(offset: usize, message: &str) -> Result<T, ParseError> {
+    Err(ParseError {
+        offset,
+        message: message.to_owned(),
+    })
+}
+
+// reads garbage starting at the `<` at `start`, returning the node and the offset after it
+fn parse_garbage(input: &[u8], start: usize) -> Result<(Node, usize), ParseError> {
+    let mut pos = start + 1;
+    let mut cancelled = 0;
+    while pos < input.len() {
+        match input[pos] {
+            b'!' => {
+                cancelled += 1;
+                pos += 2;
+            }
+            b'>' => {
+                let raw = String::from_utf8_lossy(&input[start + 1..pos]).into_owned();
+                let node = Node::Garbage {
+                    raw,
+                    cancelled,
+                    span: start..pos + 1,
+                };
+                return Ok((node, pos + 1));
+            }
+            _ => pos += 1,
+        }
+    }
+    error(start, "unterminated garbage")
+}
+
+// parses a single group, ignoring surrounding whitespace such as the final newline.
+// spans and error offsets are still counted from the start of `input`.
+// open groups are kept on an explicit stack so deep nesting can't overflow the call stack
+pub fn parse(input: &str) -> Result<Node, ParseError> {
+    let bytes = input.trim_end().as_bytes();
+    // open groups with their start offset and the children read so far
+    let mut stack: Vec<(usize, Vec<Node>)> = Vec::new();
+    let mut pos = input.len() - input.trim_start().len();
+    // whether the last thing read was a complete group or garbage
+    let mut after_item = false;
+    loop {
+        let just_opened = !after_item && stack.last().is_some_and(|g| g.1.is_empty());
+        let finished = match (after_item, bytes.get(pos)) {
+            (false, Some(&b'{')) => {
+                stack.push((pos, Vec::new()));
+                pos += 1;
+                None
+            }
+            (false, Some(&b'<')) if !stack.is_empty() => {
+                let (node, next) = parse_garbage(bytes, pos)?;
+                pos = next;
+                Some(node)
+            }
+            (true, Some(&b',')) => {
+                after_item = false;
+                pos += 1;
+                None
+            }
+            (_, Some(&b'}')) if after_item || just_opened => {
+                let (start, children) = stack.pop().unwrap();
+                pos += 1;
+                Some(Node::Group {
+                    children,
+                    span: start..pos,
+                })
+            }
+            (_, None) if !stack.is_empty() => return error(pos, "unclosed group"),
+            (false, _) if stack.is_empty() => return error(pos, "expected '{'"),
+            (false, _) => return error(pos, "expected '{', '<' or '}'"),
+            (true, _) => return error(pos, "expected ',' or '}'"),
+        };
+        if let Some(node) = finished {
+            match stack.last_mut() {
+                Some(parent) => {
+                    parent.1.push(node);
+                    after_item = true;
+                }
+                None if pos == bytes.len() => return Ok(node),
+                None => return error(pos, "unexpected data after the outermost group"),
+            }
+        }
+    }
+}
+
+// what is left to print, with the nesting level of each node and group end
+enum Step<'a> {
+    Node(&'a Node, usize),
+    Comma,
+    Close(usize),
+}
+
+// the steps for the inside of a group at `level`, last first so they pop in order
+fn push_children<'a>(steps: &mut Vec<Step<'a>>, children: &'a [Node], level: usize) {
+    steps.push(Step::Close(level));
+    for (i, child) in children.iter().enumerate().rev() {
+        steps.push(Step::Node(child, level + 1));
+        if i > 0 {
+            steps.push(Step::Comma);
+        }
+    }
+}
+
+// two spaces per level, written in pieces rather than building the whole prefix
+fn indent<W: Write>(out: &mut W, level: usize) -> io::Result<()> {
+    const SPACES: [u8; 64] = [b' '; 64];
+    let mut left = 2 * level;
+    while left > 0 {
+        let n = left.min(SPACES.len());
+        out.write_all(&SPACES[..n])?;
+        left -= n;
+    }
+    Ok(())
+}
+
+impl Node {
+    // the stream this node was parsed from
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        let mut steps = vec![Step::Node(self, 0)];
+        while let Some(step) = steps.pop() {
+            match step {
+                Step::Node(Node::Group { children, .. }, level) => {
+                    out.push('{');
+                    push_children(&mut steps, children, level);
+                }
+                Step::Node(Node::Garbage { raw, .. }, _) => {
+                    out.push('<');
+                    out.push_str(raw);
+                    out.push('>');
+                }
+                Step::Comma => out.push(','),
+                Step::Close(_) => out.push('}'),
+            }
+        }
+        out
+    }
+
+    // one node per line, nested groups indented, annotated with scores and spans.
+    // written as it goes, since the indentation grows with the square of the depth
+    pub fn pretty<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut steps = vec![Step::Node(self, 0)];
+        while let Some(step) = steps.pop() {
+            match step {
+                Step::Node(Node::Group { children, span }, level) => {
+                    let open = if children.is_empty() { "{}" } else { "{" };
+                    indent(out, level)?;
+                    writeln!(out, "{}  # score {}, bytes {}..{}", open, level + 1, span.start, span.end)?;
+                    if !children.is_empty() {
+                        push_children(&mut steps, children, level);
+                    }
+                }
+                Step::Node(&Node::Garbage { ref raw, cancelled, .. }, level) => {
+                    indent(out, level)?;
+                    writeln!(out, "<{}>  # {} cancelled", raw, cancelled)?;
+                }
+                Step::Comma => (),
+                Step::Close(level) => {
+                    indent(out, level)?;
+                    writeln!(out, "}}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// the derived drop would recurse once per level of nesting, so children are
+// flattened onto a list and dropped one at a time
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut pending = match *self {
+            Node::Group { ref mut children, .. } => mem::take(children),
+            Node::Garbage { .. } => return,
+        };
+        while let Some(mut node) = pending.pop() {
+            if let Node::Group { ref mut children, .. } = node {
+                pending.append(children);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surrounding_whitespace() {
+        let tree = parse(" \t{{<a!>>},{}}\n").unwrap();
+        assert_eq!(tree.serialize(), "{{<a!>>},{}}");
+        let mut out = Vec::new();
+        tree.pretty(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with("{  # score 1, bytes 2..14\n"));
+        assert_eq!(parse(" {x}").unwrap_err().offset, 2);
+    }
+
+    fn nested(depth: usize) -> String {
+        "{".repeat(depth) + &"}".repeat(depth)
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let input = nested(1_000_000);
+        let tree = parse(&input).unwrap();
+        assert_eq!(tree.serialize(), input);
+        // the indentation alone makes deeper trees too slow to print here
+        parse(&nested(20_000)).unwrap().pretty(&mut io::sink()).unwrap();
+    }
+}
@@ -1,11 +1,18 @@
+use std::env;
+use std::process;
 use std::io::{self, Read};
 
+mod ast;
+mod stream;
+
+#[derive(Clone, Copy)]
 enum State {
     Default,
     Garbage,
     Cancel,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 struct Score {
     score: i32,
     num_groups: i32,
@@ -45,21 +52,66 @@ fn transition(c: char, s: State, score: &mut Score) -> State {
     }
 }
 
-fn count(input: &str) -> Score {
-    let mut score = Score{score: 0, num_groups: 0, level: 0, garbage_chars: 0};
-    let mut state = State::Default;
-    for c in input.chars() {
-        state = transition(c, state, &mut score);
-    }
-    score
-}
-
-
 fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    // malformed streams are reported either way, but only fail in strict mode
+    let strict = args.iter().any(|a| a == "--strict");
+    let mode = args.iter().find(|a| !a.starts_with("--")).map(|s| s.as_str());
     let mut stdin = io::stdin();
-    let mut input = String::new();
-    if let Ok(_) = stdin.read_to_string(&mut input) {
-        let score = count(&input);
-        println!("Input had {} groups with a total score of {} and {} pieces of garbage", score.num_groups, score.score, score.garbage_chars);
+    match mode {
+        Some("pretty") | Some("serialize") => {
+            let mut input = String::new();
+            if stdin.read_to_string(&mut input).is_ok() {
+                match ast::parse(&input) {
+                    Ok(tree) if mode == Some("pretty") => {
+                        // a closed pipe just ends the output early
+                        let stdout = io::stdout();
+                        let _ = tree.pretty(&mut io::BufWriter::new(stdout.lock()));
+                    }
+                    Ok(tree) => println!("{}", tree.serialize()),
+                    Err(err) => println!("Error at byte {}: {}", err.offset, err.message),
+                }
+            }
+            return;
+        }
+        _ => (),
+    }
+    let show_events = mode == Some("events");
+    let mut errors = 0;
+    let mut handle = |event: stream::Event| {
+        if let stream::Event::Error(ref diagnostic) = event {
+            errors += 1;
+            if strict {
+                println!("Error: {}", diagnostic);
+                process::exit(1);
+            }
+            if !show_events {
+                println!("Warning: {}", diagnostic);
+            }
+        }
+        if show_events {
+            println!("{:?}", event);
+        }
+    };
+    let mut parser = stream::StreamParser::new();
+    // the stream is handled a chunk at a time, so it never has to fit in memory
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let n = match stdin.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => {
+                println!("Error reading input: {}", err);
+                return;
+            }
+        };
+        parser.feed(&buffer[..n], &mut handle);
+    }
+    parser.finish(&mut handle);
+    let score = parser.score();
+    println!("Input had {} groups with a total score of {} and {} pieces of garbage", score.num_groups, score.score, score.garbage_chars);
+    if errors > 0 {
+        println!("{} problems found in the stream", errors);
     }
 }
@@ -0,0 +1,276 @@
+use std::fmt;
+
+use super::{transition, Score, State};
+
+#[derive(Debug, PartialEq)]
+pub enum ErrorKind {
+    UnexpectedClose,
+    UnclosedGroup,
+    UnterminatedGarbage,
+    TrailingCancel,
+}
+
+// a malformed part of the stream, `offset` points at the byte that caused it
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub offset: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self.kind {
+            ErrorKind::UnexpectedClose => "'}' without an open group",
+            ErrorKind::UnclosedGroup => "group is never closed",
+            ErrorKind::UnterminatedGarbage => "garbage is never closed",
+            ErrorKind::TrailingCancel => "stream ends with a '!' that has nothing to cancel",
+        };
+        write!(f, "byte {}: {}", self.offset, message)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    GroupOpen {
+        offset: usize,
+        depth: i32,
+    },
+    // `depth` is also the score of the group
+    GroupClose {
+        offset: usize,
+        depth: i32,
+        total_score: i32,
+    },
+    // `end` is the offset of the closing `>`
+    Garbage {
+        start: usize,
+        end: usize,
+        chars: i32,
+        cancelled: i32,
+    },
+    Error(Diagnostic),
+}
+
+// a push parser: feed it the stream in chunks of any size, and it keeps just enough
+// state to carry a group, garbage block or `!` cancel over to the next chunk
+pub struct StreamParser {
+    state: State,
+    score: Score,
+    // offset of the next byte
+    offset: usize,
+    garbage_start: usize,
+    garbage_chars: i32,
+    garbage_cancelled: i32,
+    cancel_offset: usize,
+    // offsets of the groups that are still open
+    open_groups: Vec<usize>,
+}
+
+impl StreamParser {
+    pub fn new() -> StreamParser {
+        StreamParser {
+            state: State::Default,
+            score: Score {
+                score: 0,
+                num_groups: 0,
+                level: 0,
+                garbage_chars: 0,
+            },
+            offset: 0,
+            garbage_start: 0,
+            garbage_chars: 0,
+            garbage_cancelled: 0,
+            cancel_offset: 0,
+            open_groups: Vec::new(),
+        }
+    }
+
+    pub fn feed<F: FnMut(Event)>(&mut self, chunk: &[u8], mut on_event: F) {
+        for &byte in chunk {
+            let offset = self.offset;
+            self.offset += 1;
+            // the tail of a multi-byte character; the first byte already stood for all of it
+            if byte & 0xc0 == 0x80 {
+                continue;
+            }
+            let c = byte as char;
+            match (self.state, c) {
+                (State::Default, '{') => {
+                    self.open_groups.push(offset);
+                    on_event(Event::GroupOpen {
+                        offset,
+                        depth: self.score.level + 1,
+                    })
+                }
+                (State::Default, '}') => {
+                    match self.open_groups.pop() {
+                        Some(_) => {
+                            on_event(Event::GroupClose {
+                                offset,
+                                depth: self.score.level,
+                                total_score: self.score.score + self.score.level,
+                            })
+                        }
+                        None => {
+                            // skipped, so the nesting level never goes negative
+                            on_event(Event::Error(Diagnostic {
+                                offset,
+                                kind: ErrorKind::UnexpectedClose,
+                            }));
+                            continue;
+                        }
+                    }
+                }
+                (State::Default, '<') => {
+                    self.garbage_start = offset;
+                    self.garbage_chars = self.score.garbage_chars;
+                    self.garbage_cancelled = 0;
+                }
+                (State::Garbage, '!') => {
+                    self.garbage_cancelled += 1;
+                    self.cancel_offset = offset;
+                }
+                (State::Garbage, '>') => {
+                    on_event(Event::Garbage {
+                        start: self.garbage_start,
+                        end: offset,
+                        chars: self.score.garbage_chars - self.garbage_chars,
+                        cancelled: self.garbage_cancelled,
+                    })
+                }
+                _ => (),
+            }
+            self.state = transition(c, self.state, &mut self.score);
+        }
+    }
+
+    // reports whatever the stream left unfinished
+    pub fn finish<F: FnMut(Event)>(&mut self, mut on_event: F) {
+        if let State::Cancel = self.state {
+            on_event(Event::Error(Diagnostic {
+                offset: self.cancel_offset,
+                kind: ErrorKind::TrailingCancel,
+            }));
+        }
+        if let State::Garbage | State::Cancel = self.state {
+            on_event(Event::Error(Diagnostic {
+                offset: self.garbage_start,
+                kind: ErrorKind::UnterminatedGarbage,
+            }));
+        }
+        for &offset in self.open_groups.iter().rev() {
+            on_event(Event::Error(Diagnostic {
+                offset,
+                kind: ErrorKind::UnclosedGroup,
+            }));
+        }
+    }
+
+    // totals for everything fed so far
+    pub fn score(&self) -> &Score {
+        &self.score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run<'a, I: IntoIterator<Item = &'a [u8]>>(chunks: I) -> (Vec<Event>, Score) {
+        let mut parser = StreamParser::new();
+        let mut events = Vec::new();
+        for chunk in chunks {
+            parser.feed(chunk, |event| events.push(event));
+        }
+        parser.finish(|event| events.push(event));
+        (events, parser.score().clone())
+    }
+
+    #[test]
+    fn chunk_boundaries() {
+        let inputs = ["{<a!>b>,{!}}}", "{<{!>}>}", "{{<!!>},{<a>,<\u{fc}!>>}}", "{<ab!"];
+        for input in inputs.iter() {
+            let bytes = input.as_bytes();
+            let whole = run(vec![bytes]);
+            for size in 1..3 {
+                assert_eq!(run(bytes.chunks(size)), whole, "{} in chunks of {}", input, size);
+            }
+            for split in 0..bytes.len() {
+                let (a, b) = bytes.split_at(split);
+                assert_eq!(run(vec![a, b]), whole, "{} split at {}", input, split);
+            }
+
+            // unless something was wrong with the stream, the totals are those of the plain state machine
+            if whole.0.iter().any(|event| matches!(*event, Event::Error(_))) {
+                continue;
+            }
+            let mut state = State::Default;
+            let mut score = Score {
+                score: 0,
+                num_groups: 0,
+                level: 0,
+                garbage_chars: 0,
+            };
+            for c in input.chars() {
+                state = transition(c, state, &mut score);
+            }
+            assert_eq!(whole.1, score, "{}", input);
+        }
+
+        let (events, score) = run(vec!["{<a!>b>,{!}}}".as_bytes()]);
+        assert_eq!(events,
+                   vec![Event::GroupOpen { offset: 0, depth: 1 },
+                        Event::Garbage { start: 1, end: 6, chars: 2, cancelled: 1 },
+                        Event::GroupOpen { offset: 8, depth: 2 },
+                        Event::GroupClose { offset: 10, depth: 2, total_score: 2 },
+                        Event::GroupClose { offset: 11, depth: 1, total_score: 3 },
+                        Event::Error(Diagnostic { offset: 12, kind: ErrorKind::UnexpectedClose })]);
+        assert_eq!((score.score, score.num_groups, score.garbage_chars), (3, 2, 2));
+    }
+
+    fn errors(input: &str) -> Vec<Diagnostic> {
+        run(vec![input.as_bytes()]).0
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Error(diagnostic) => Some(diagnostic),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn at(offset: usize, kind: ErrorKind) -> Diagnostic {
+        Diagnostic { offset, kind }
+    }
+
+    #[test]
+    fn unexpected_close() {
+        assert_eq!(errors("{{}}}"), vec![at(4, ErrorKind::UnexpectedClose)]);
+        assert_eq!(errors("}{}"), vec![at(0, ErrorKind::UnexpectedClose)]);
+    }
+
+    #[test]
+    fn trailing_cancel() {
+        assert_eq!(errors("{<ab!"),
+                   vec![at(4, ErrorKind::TrailingCancel),
+                        at(1, ErrorKind::UnterminatedGarbage),
+                        at(0, ErrorKind::UnclosedGroup)]);
+    }
+
+    #[test]
+    fn unterminated_garbage() {
+        assert_eq!(errors("{<a!>b}"),
+                   vec![at(1, ErrorKind::UnterminatedGarbage), at(0, ErrorKind::UnclosedGroup)]);
+        assert_eq!(errors("<>{}<"), vec![at(4, ErrorKind::UnterminatedGarbage)]);
+    }
+
+    #[test]
+    fn unclosed_groups() {
+        // innermost first, each at its own opening brace
+        assert_eq!(errors("{{<a>},{{}"),
+                   vec![at(7, ErrorKind::UnclosedGroup), at(0, ErrorKind::UnclosedGroup)]);
+        assert_eq!(errors("{{{"),
+                   vec![at(2, ErrorKind::UnclosedGroup),
+                        at(1, ErrorKind::UnclosedGroup),
+                        at(0, ErrorKind::UnclosedGroup)]);
+    }
+}
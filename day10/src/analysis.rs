@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use knothash::{self, Digest};
+
+const DIGEST_BITS: u32 = 128;
+
+// keys shaped like the ones day14 hashes
+pub fn generate_keys(prefix: &str, count: usize) -> Vec<String> {
+    (0..count).map(|i| format!("{}-{}", prefix, i)).collect()
+}
+
+fn differing_bits(a: &Digest, b: &Digest) -> u32 {
+    a.0.iter().zip(b.0.iter()).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+// the first `bits` bits of the digest, `bits` at most 64
+fn prefix(digest: &Digest, bits: u32) -> u64 {
+    let top = digest.0[..8].iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    if bits == 64 {
+        top
+    } else {
+        top >> (64 - bits)
+    }
+}
+
+pub struct Buckets {
+    pub bits: u32,
+    pub min: usize,
+    pub max: usize,
+    pub expected: f64,
+    pub chi_squared: f64,
+}
+
+// how evenly the keys spread over 2^bits buckets picked by the digest prefix,
+// or None without any keys to spread
+pub fn buckets(digests: &[Digest], bits: u32) -> Option<Buckets> {
+    if digests.is_empty() {
+        return None;
+    }
+    let mut counts = vec![0usize; 1 << bits];
+    for digest in digests {
+        counts[prefix(digest, bits) as usize] += 1;
+    }
+    let expected = digests.len() as f64 / counts.len() as f64;
+    let chi_squared = counts
+        .iter()
+        .map(|&c| (c as f64 - expected).powi(2) / expected)
+        .sum();
+    Some(Buckets {
+        bits,
+        min: *counts.iter().min().unwrap(),
+        max: *counts.iter().max().unwrap(),
+        expected,
+        chi_squared,
+    })
+}
+
+pub struct Avalanche {
+    pub samples: usize,
+    pub mean: f64,
+    pub stddev: f64,
+    pub min: u32,
+    pub max: u32,
+    // the output bit whose flip rate is furthest from one half, and that rate
+    pub worst_bit: usize,
+    pub worst_rate: f64,
+}
+
+// flips every bit of every key in turn and counts the output bits that change.
+// None if there was no bit to flip
+pub fn avalanche(keys: &[String]) -> Option<Avalanche> {
+    let mut flips: Vec<u32> = Vec::new();
+    let mut per_bit = vec![0usize; DIGEST_BITS as usize];
+    for key in keys {
+        let original = knothash::hash(key.as_bytes());
+        let mut bytes = key.clone().into_bytes();
+        for i in 0..bytes.len() * 8 {
+            bytes[i / 8] ^= 1 << (i % 8);
+            let flipped = knothash::hash(&bytes);
+            bytes[i / 8] ^= 1 << (i % 8);
+            flips.push(differing_bits(&original, &flipped));
+            for (bit, count) in per_bit.iter_mut().enumerate() {
+                let mask = 0x80 >> (bit % 8);
+                if (original.0[bit / 8] ^ flipped.0[bit / 8]) & mask != 0 {
+                    *count += 1;
+                }
+            }
+        }
+    }
+    let samples = flips.len();
+    if samples == 0 {
+        return None;
+    }
+    let mean = flips.iter().map(|&f| f as f64).sum::<f64>() / samples as f64;
+    let variance = flips.iter().map(|&f| (f as f64 - mean).powi(2)).sum::<f64>() / samples as f64;
+    let rates: Vec<f64> = per_bit.iter().map(|&c| c as f64 / samples as f64).collect();
+    let worst_bit = (0..rates.len())
+        .max_by(|&a, &b| (rates[a] - 0.5).abs().partial_cmp(&(rates[b] - 0.5).abs()).unwrap())
+        .unwrap();
+    Some(Avalanche {
+        samples,
+        mean,
+        stddev: variance.sqrt(),
+        min: *flips.iter().min().unwrap(),
+        max: *flips.iter().max().unwrap(),
+        worst_bit,
+        worst_rate: rates[worst_bit],
+    })
+}
+
+pub struct Collisions {
+    pub bits: u32,
+    pub distinct: usize,
+    pub collisions: usize,
+    pub expected: f64,
+    // the first pair of keys found sharing a prefix
+    pub example: Option<(usize, usize)>,
+}
+
+// keys whose digests agree on the first `bits` bits with an earlier key
+pub fn collisions(digests: &[Digest], bits: u32) -> Collisions {
+    let mut seen: HashMap<u64, usize> = HashMap::new();
+    let mut collisions = 0;
+    let mut example = None;
+    for (i, digest) in digests.iter().enumerate() {
+        let first = *seen.entry(prefix(digest, bits)).or_insert(i);
+        if first != i {
+            collisions += 1;
+            example = example.or(Some((first, i)));
+        }
+    }
+    // for an ideal hash: keys minus the number of buckets expected to be occupied,
+    // with the series expansion when the difference is too small for the direct formula
+    let space = 2f64.powi(bits as i32);
+    let n = digests.len() as f64;
+    let x = n / space;
+    let expected = if x < 1e-4 {
+        space * x * x / 2.0 * (1.0 - x / 3.0)
+    } else {
+        n - space * (1.0 - (-x).exp())
+    };
+    Collisions {
+        bits,
+        distinct: seen.len(),
+        collisions,
+        expected,
+        example,
+    }
+}
+
+pub fn report(prefix_key: &str, count: usize, avalanche_keys: usize) {
+    let keys = generate_keys(prefix_key, count);
+    let digests: Vec<Digest> = keys.iter().map(|k| knothash::hash(k.as_bytes())).collect();
+    match count {
+        0 => println!("0 keys"),
+        _ => println!("{} keys \"{}-0\" .. \"{}-{}\"", count, prefix_key, prefix_key, count - 1),
+    }
+
+    println!();
+    println!("bucket distribution (buckets chosen by the leading bits)");
+    println!("{:>7} {:>10} {:>7} {:>7} {:>12} {:>12}", "buckets", "expected", "min", "max", "chi^2", "chi^2/df");
+    for &bits in [4, 8, 12, 16].iter() {
+        let b = match buckets(&digests, bits) {
+            Some(b) => b,
+            None => {
+                println!("no keys to spread");
+                break;
+            }
+        };
+        let df = ((1u64 << bits) - 1) as f64;
+        println!("{:>7} {:>10.2} {:>7} {:>7} {:>12.1} {:>12.3}",
+                 1u64 << b.bits, b.expected, b.min, b.max, b.chi_squared, b.chi_squared / df);
+    }
+
+    println!();
+    println!("avalanche (output bits changed per flipped input bit, ideal {})", DIGEST_BITS / 2);
+    match avalanche(&keys[..avalanche_keys.min(keys.len())]) {
+        Some(a) => {
+            println!("{:>8} {:>8} {:>8} {:>5} {:>5} {:>10} {:>10}",
+                     "samples", "mean", "stddev", "min", "max", "worst bit", "flip rate");
+            println!("{:>8} {:>8.3} {:>8.3} {:>5} {:>5} {:>10} {:>10.4}",
+                     a.samples, a.mean, a.stddev, a.min, a.max, a.worst_bit, a.worst_rate);
+        }
+        None => println!("no keys to flip bits in"),
+    }
+
+    println!();
+    println!("collisions on truncated digests");
+    println!("{:>4} {:>9} {:>10} {:>10}  example", "bits", "distinct", "collisions", "expected");
+    for &bits in [16, 24, 32, 40, 48, 56, 64].iter() {
+        let c = collisions(&digests, bits);
+        let example = match c.example {
+            Some((a, b)) => format!("{} / {}", keys[a], keys[b]),
+            None => "-".to_owned(),
+        };
+        println!("{:>4} {:>9} {:>10} {:>10.2}  {}", c.bits, c.distinct, c.collisions, c.expected, example);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_keys() {
+        assert!(buckets(&[], 4).is_none());
+        assert!(avalanche(&[]).is_none());
+        assert!(avalanche(&["".to_owned()]).is_none());
+        let c = collisions(&[], 16);
+        assert_eq!((c.distinct, c.collisions, c.expected), (0, 0, 0.0));
+    }
+
+    #[test]
+    fn few_keys() {
+        let keys = generate_keys("fixture", 3);
+        let digests: Vec<Digest> = keys.iter().map(|k| knothash::hash(k.as_bytes())).collect();
+        let b = buckets(&digests, 4).unwrap();
+        assert_eq!(b.expected, 3.0 / 16.0);
+        assert!(b.chi_squared.is_finite());
+        let a = avalanche(&keys[..1]).unwrap();
+        assert_eq!(a.samples, 8 * keys[0].len());
+        assert!(a.min <= a.max && a.max <= DIGEST_BITS);
+    }
+}
@@ -1,45 +1,11 @@
+use std::env;
 use std::io;
-use std::fmt::Write;
-
-fn reverse<T>(list: &mut [T], mut start: usize, mut len: usize) {
-    while len > 1 {
-        start = start % list.len();
-        let endpos = (start + len - 1) % list.len();
-        list.swap(start, endpos);
-        start = start + 1;
-        len -= 2;
-    }
-}
-
-fn knothash(list: &mut [u32], lengths: &[usize]) {
-    let mut skipsize = 0;
-    let mut pos = 0;
-    for &length in lengths {
-        reverse(list, pos, length);
-        pos = (pos + length + skipsize) % list.len();
-        skipsize += 1;
-    }
-}
+use std::time::Instant;
 
-fn knothash2(list: &mut [u32], lengths: &[usize]) {
-    let mut skipsize = 0;
-    let mut pos = 0;
-    for _ in 0..64 {
-        for &length in lengths {
-            reverse(list, pos, length);
-            pos = (pos + length + skipsize) % list.len();
-            skipsize += 1;
-        }
-    }
-}
+extern crate knothash;
+use knothash::{Knot, KnotHashParams};
 
-fn to_hex_string(list: &[u32]) -> String {
-    let mut result = String::new();
-    for value in list.chunks(16).map(|subslice| subslice.iter().fold(0, |a, &b| a ^ b)) {
-        write!(&mut result, "{:02x}", value).unwrap();
-    }
-    result
-}
+mod analysis;
 
 fn parse_input1(line: &str) -> Option<Vec<usize>> {
     let mut res = Vec::new();
@@ -51,33 +17,163 @@ fn parse_input1(line: &str) -> Option<Vec<usize>> {
             return None;
         }
     }
-    return Some(res);
+    Some(res)
 }
 
-fn parse_input2(line: &str) -> Vec<usize> {
-    let mut result = Vec::new();
-    for byte in line.bytes() {
-        result.push(byte as usize);
+// reads `--size N --rounds N --suffix a,b,... --block N`, each optional
+fn parse_params<I: Iterator<Item = String>>(mut args: I) -> Result<KnotHashParams, String> {
+    let mut params = KnotHashParams::default();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", flag))?;
+        let number = |value: &str| {
+            value.parse::<usize>().map_err(|_| format!("invalid value for {}: {}", flag, value))
+        };
+        match flag.as_str() {
+            "--size" => params.list_size = number(&value)?,
+            "--rounds" => params.rounds = number(&value)?,
+            "--block" => params.block_size = number(&value)?,
+            "--suffix" => {
+                params.suffix = value
+                    .split(',')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.trim().parse::<u8>().map_err(|_| format!("invalid suffix value: {}", s)))
+                    .collect::<Result<_, _>>()?
+            }
+            _ => return Err(format!("unknown option {}", flag)),
+        }
     }
-    result.extend(&[17, 31, 73, 47, 23]);
-    result
+    params.validate()?;
+    Ok(params)
+}
+
+fn custom_hash<I: Iterator<Item = String>>(args: I) {
+    let params = match parse_params(args) {
+        Ok(params) => params,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line).is_ok() {
+        match params.hash_hex(line.trim().as_bytes()) {
+            Ok(hex) => println!("{}", hex),
+            Err(err) => println!("{}", err),
+        }
+    }
+}
+
+// hashes `count` keys the way day14 builds its rows, once with each kernel
+fn benchmark<I: Iterator<Item = String>>(mut args: I) {
+    let count = args.next().and_then(|s| s.parse::<usize>().ok()).unwrap_or(10_000);
+    let key = args.next().unwrap_or_else(|| "flqrgnkx".to_owned());
+    let keys: Vec<String> = (0..count).map(|i| format!("{}-{}", key, i)).collect();
+    let params = KnotHashParams::default();
+
+    let timed = |reference: bool| {
+        let start = Instant::now();
+        let hashes: Vec<Vec<u32>> = keys
+            .iter()
+            .map(|k| if reference { params.hash_reference(k.as_bytes()) } else { params.hash(k.as_bytes()) })
+            .map(Result::unwrap)
+            .collect();
+        (hashes, start.elapsed())
+    };
+    let (fast, fast_time) = timed(false);
+    let (reference, reference_time) = timed(true);
+
+    let mismatches = fast.iter().zip(reference.iter()).filter(|&(a, b)| a != b).count();
+    println!("{} keys", count);
+    println!("reference: {:?}", reference_time);
+    println!("rotating:  {:?}", fast_time);
+    println!("speedup:   {:.2}x", reference_time.as_secs_f64() / fast_time.as_secs_f64());
+    println!("mismatches: {}", mismatches);
 }
 
 fn main() {
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("custom") => return custom_hash(args),
+        Some("bench") => return benchmark(args),
+        Some("analyze") => {
+            let count = args.next().and_then(|s| s.parse().ok()).unwrap_or(100_000);
+            let key = args.next().unwrap_or_else(|| "fixture".to_owned());
+            let avalanche_keys = args.next().and_then(|s| s.parse().ok()).unwrap_or(500);
+            return analysis::report(&key, count, avalanche_keys);
+        }
+        _ => (),
+    }
     let mut line = String::new();
-    if let Ok(_) = io::stdin().read_line(&mut line) {
-        {
-            let lengths = parse_input1(&line).unwrap();
-            let mut arr = (0..256).collect::<Vec<_>>();
-            knothash(&mut arr, &lengths);
-            println!("part 1: {}", arr[0] * arr[1]);
+    if io::stdin().read_line(&mut line).is_ok() {
+        // part 1 only makes sense when the line is a list of numbers
+        if let Some(lengths) = parse_input1(&line) {
+            match lengths.iter().find(|&&l| l > knothash::LIST_SIZE) {
+                Some(length) => {
+                    println!("part 1: length {} does not fit in a list of {}", length, knothash::LIST_SIZE)
+                }
+                None => {
+                    let mut knot = Knot::new(knothash::LIST_SIZE);
+                    knot.round(&lengths);
+                    println!("part 1: {}", knot.list[0] * knot.list[1]);
+                }
+            }
         }
         {
-            let lengths = parse_input2(line.trim());
-            let mut arr = (0..256).collect::<Vec<_>>();
-            knothash2(&mut arr, &lengths);
-            println!("part 2: {}", to_hex_string(&arr));
+            let digest = knothash::hash(line.trim().as_bytes());
+            println!("part 2: {}", digest);
+
+        }
+    }
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(str::to_owned)
+    }
+
+    // published example hashes, all computed with the standard parameters
+    const EXAMPLES: [(&str, &str); 4] = [
+        ("", "a2582a3a0e66e6e86e3812dcb672a272"),
+        ("AoC 2017", "33efeb34ea91902bb2f59c9920caa6cd"),
+        ("1,2,3", "3efbe78a8d82f29979031a4aa0b16a9d"),
+        ("1,2,4", "63960835bcdc130f0b66d7ff4f6a5a8e"),
+    ];
+
+    #[test]
+    fn default_params() {
+        let params = parse_params(args("")).unwrap();
+        assert_eq!(params, KnotHashParams::default());
+        for &(input, expected) in EXAMPLES.iter() {
+            assert_eq!(params.hash_hex(input.as_bytes()).unwrap(), expected, "input {:?}", input);
         }
     }
+
+    #[test]
+    fn small_list() {
+        let lengths = parse_input1("3,4,1,5").unwrap();
+        let mut knot = Knot::new(5);
+        knot.round(&lengths);
+        assert_eq!(knot.list, [3, 4, 2, 1, 0]);
+        assert_eq!(knot.list[0] * knot.list[1], 12);
+
+        // a single round with no suffix and the whole list as one block is the same knot
+        let params = parse_params(args("--size 5 --rounds 1 --suffix , --block 5")).unwrap();
+        assert_eq!(params.hash(&[3, 4, 1, 5]).unwrap(), [3 ^ 4 ^ 2 ^ 1]);
+        assert!(params.hash(&[6]).is_err());
+    }
+
+    #[test]
+    fn rejects_empty_sizes() {
+        assert!(parse_params(args("--size 0")).is_err());
+        assert!(parse_params(args("--block 0")).is_err());
+        assert!(parse_params(args("--size")).is_err());
+        let params = KnotHashParams { list_size: 0, ..KnotHashParams::default() };
+        assert!(params.validate().is_err());
+        let params = KnotHashParams { block_size: 0, ..KnotHashParams::default() };
+        assert!(params.validate().is_err());
+        assert!(params.hash(b"").is_err());
+    }
 }
@@ -0,0 +1,359 @@
+use std::ops::{Add, Mul, Sub};
+
+// flat topped hexes in axial coordinates. the third cube coordinate `s` is implied by
+// q + r + s = 0. `q` grows towards the south-east and `r` towards the south
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    N,
+    NE,
+    SE,
+    S,
+    SW,
+    NW,
+}
+
+pub const DIRECTIONS: [Direction; 6] = [
+    Direction::N,
+    Direction::NE,
+    Direction::SE,
+    Direction::S,
+    Direction::SW,
+    Direction::NW,
+];
+
+impl Direction {
+    pub fn parse(s: &str) -> Option<Direction> {
+        match s {
+            "n" => Some(Direction::N),
+            "ne" => Some(Direction::NE),
+            "se" => Some(Direction::SE),
+            "s" => Some(Direction::S),
+            "sw" => Some(Direction::SW),
+            "nw" => Some(Direction::NW),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::N => "n",
+            Direction::NE => "ne",
+            Direction::SE => "se",
+            Direction::S => "s",
+            Direction::SW => "sw",
+            Direction::NW => "nw",
+        }
+    }
+
+    pub fn unit(self) -> Hex {
+        match self {
+            Direction::N => Hex::new(0, -1),
+            Direction::NE => Hex::new(1, -1),
+            Direction::SE => Hex::new(1, 0),
+            Direction::S => Hex::new(0, 1),
+            Direction::SW => Hex::new(-1, 1),
+            Direction::NW => Hex::new(-1, 0),
+        }
+    }
+
+    // position in `DIRECTIONS`
+    pub fn index(self) -> usize {
+        DIRECTIONS.iter().position(|&d| d == self).unwrap()
+    }
+
+    // the next direction clockwise, `turns` times
+    pub fn turn(self, turns: i32) -> Direction {
+        DIRECTIONS[(self.index() as i32 + turns).rem_euclid(6) as usize]
+    }
+
+    pub fn opposite(self) -> Direction {
+        self.turn(3)
+    }
+}
+
+impl Hex {
+    pub fn new(q: i32, r: i32) -> Hex {
+        Hex { q, r }
+    }
+
+    pub fn s(&self) -> i32 {
+        -self.q - self.r
+    }
+
+    pub fn neighbour(self, direction: Direction) -> Hex {
+        self + direction.unit()
+    }
+
+    // steps needed to get here from the origin
+    pub fn length(&self) -> i32 {
+        (self.q.abs() + self.r.abs() + self.s().abs()) / 2
+    }
+
+    // a shortest sequence of moves to `other`. every shortest path only uses two
+    // neighbouring directions; the canonical one takes them in the order of `DIRECTIONS`
+    pub fn path_to(&self, other: Hex) -> Vec<Direction> {
+        let diff = other - *self;
+        for i in 0..6 {
+            let (first, second) = (DIRECTIONS[i], DIRECTIONS[(i + 1) % 6]);
+            let (u, v) = (first.unit(), second.unit());
+            // solve diff = a * u + b * v; neighbouring units always have determinant 1
+            let det = u.q * v.r - u.r * v.q;
+            let a = (diff.q * v.r - diff.r * v.q) * det;
+            let b = (u.q * diff.r - u.r * diff.q) * det;
+            if a >= 0 && b >= 0 {
+                let (mut moves, rest) = if i < 5 {
+                    (vec![first; a as usize], vec![second; b as usize])
+                } else {
+                    (vec![second; b as usize], vec![first; a as usize])
+                };
+                moves.extend(rest);
+                return moves;
+            }
+        }
+        unreachable!("the six sextants cover the whole grid")
+    }
+}
+
+// the rest of the grid operations; only the tests use them so far
+#[cfg(test)]
+impl Hex {
+    pub fn neighbours(self) -> [Hex; 6] {
+        let mut result = [self; 6];
+        for (hex, &direction) in result.iter_mut().zip(DIRECTIONS.iter()) {
+            *hex = self.neighbour(direction);
+        }
+        result
+    }
+
+    pub fn distance(&self, other: Hex) -> i32 {
+        (*self - other).length()
+    }
+
+    // the hexes exactly `radius` steps away, clockwise starting from the north
+    pub fn ring(&self, radius: i32) -> Vec<Hex> {
+        if radius == 0 {
+            return vec![*self];
+        }
+        let mut result = Vec::new();
+        let mut hex = *self + Direction::N.unit() * radius;
+        // walking a side of the ring, going round from the northern corner
+        for &direction in DIRECTIONS.iter() {
+            for _ in 0..radius {
+                result.push(hex);
+                hex = hex.neighbour(direction.turn(2));
+            }
+        }
+        result
+    }
+
+    // the hexes a straight line from here to `other` passes through, both ends included
+    pub fn line_to(&self, other: Hex) -> Vec<Hex> {
+        let steps = self.distance(other);
+        if steps == 0 {
+            return vec![*self];
+        }
+        // nudged off centre so points on an edge between two hexes round consistently
+        let (q0, r0) = (self.q as f64 + 1e-6, self.r as f64 + 1e-6);
+        let (q1, r1) = (other.q as f64 + 1e-6, other.r as f64 + 1e-6);
+        (0..steps + 1)
+            .map(|i| {
+                let t = i as f64 / steps as f64;
+                round(q0 + (q1 - q0) * t, r0 + (r1 - r0) * t)
+            })
+            .collect()
+    }
+
+    // a sixth of a turn around the origin, clockwise for positive `turns`
+    pub fn rotate(&self, turns: i32) -> Hex {
+        let mut hex = *self;
+        for _ in 0..turns.rem_euclid(6) {
+            hex = Hex::new(-hex.r, -hex.s());
+        }
+        hex
+    }
+
+    pub fn rotate_around(&self, centre: Hex, turns: i32) -> Hex {
+        (*self - centre).rotate(turns) + centre
+    }
+
+    // the column/row layout of the puzzle, where y points north and odd columns sit
+    // half a hex lower than even ones: north-east of (0, 0) is (1, 0), but north-east
+    // of (1, 0) is (2, 1)
+    pub fn to_offset(self) -> (i32, i32) {
+        let row = self.r + (self.q + (self.q & 1)) / 2;
+        (self.q, -row)
+    }
+
+    pub fn from_offset(x: i32, y: i32) -> Hex {
+        Hex::new(x, -y - (x + (x & 1)) / 2)
+    }
+}
+
+// the hex containing a fractional axial position
+#[cfg(test)]
+fn round(q: f64, r: f64) -> Hex {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    // the coordinate that was rounded the most is the one to fix up
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    Hex::new(rq as i32, rr as i32)
+}
+
+impl Add for Hex {
+    type Output = Hex;
+    fn add(self, other: Hex) -> Hex {
+        Hex::new(self.q + other.q, self.r + other.r)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+    fn sub(self, other: Hex) -> Hex {
+        Hex::new(self.q - other.q, self.r - other.r)
+    }
+}
+
+impl Mul<i32> for Hex {
+    type Output = Hex;
+    fn mul(self, factor: i32) -> Hex {
+        Hex::new(self.q * factor, self.r * factor)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::cmp;
+
+    use super::*;
+
+    // xorshift64, also used by the walk tests
+    pub struct Rng(pub u64);
+
+    impl Rng {
+        pub fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        pub fn range(&mut self, limit: i32) -> i32 {
+            (self.next() % (2 * limit as u64 + 1)) as i32 - limit
+        }
+
+        pub fn direction(&mut self) -> Direction {
+            DIRECTIONS[(self.next() % 6) as usize]
+        }
+    }
+
+    // the original representation, kept to check against:
+    // representing locations relative to current with points,
+    // the actual direction to go on the west and east directions
+    // is different on odd and even x coordinates. this way, going north and then west
+    // leads to the same hex as going northwest twice
+    // where the grid is shifted somewhat: 1,0 is northeast of 0,0 and 1,-1 is southeast of the current
+    // location. In this configuration, going vertically, horizontally and diagonally is all one step.
+    fn offset_step(x: i32, y: i32, direction: Direction) -> (i32, i32) {
+        let even = x & 1 == 0;
+        match direction {
+            Direction::N => (x, y + 1),
+            Direction::S => (x, y - 1),
+            Direction::NE => if even { (x + 1, y) } else { (x + 1, y + 1) },
+            Direction::NW => if even { (x - 1, y) } else { (x - 1, y + 1) },
+            Direction::SE => if even { (x + 1, y - 1) } else { (x + 1, y) },
+            Direction::SW => if even { (x - 1, y - 1) } else { (x - 1, y) },
+        }
+    }
+
+    fn get_distance(x: i32, y: i32) -> i32 {
+        let abs_x = x.abs();
+        let abs_y = if y >= 0 {
+            y
+        } else {
+            if x & 1 == 0 { y.abs() } else { y.abs() - 1 }
+        };
+        // for every 2 movements in x direction you can move once in y direction
+        let diagonals = cmp::min(abs_y, abs_x / 2);
+        let x_remainder = abs_x - diagonals;
+        let y_remainder = abs_y - diagonals;
+        diagonals + x_remainder + y_remainder
+    }
+
+    #[test]
+    fn offsets() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..100_000 {
+            let (x, y) = (rng.range(1000), rng.range(1000));
+            let hex = Hex::from_offset(x, y);
+            assert_eq!(hex.to_offset(), (x, y));
+            assert_eq!(hex.length(), get_distance(x, y), "position ({}, {})", x, y);
+            let direction = rng.direction();
+            assert_eq!(hex.neighbour(direction).to_offset(), offset_step(x, y, direction));
+        }
+    }
+
+    #[test]
+    fn walks() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..100 {
+            let (mut x, mut y) = (0, 0);
+            let mut hex = Hex::default();
+            for _ in 0..1000 {
+                let direction = rng.direction();
+                let next = offset_step(x, y, direction);
+                x = next.0;
+                y = next.1;
+                hex = hex.neighbour(direction);
+                assert_eq!(hex.to_offset(), (x, y));
+                assert_eq!(hex.length(), get_distance(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn rings_and_lines() {
+        let mut rng = Rng(0xd1b5_4a32_d192_ed03);
+        for _ in 0..1000 {
+            let a = Hex::new(rng.range(50), rng.range(50));
+            let b = Hex::new(rng.range(50), rng.range(50));
+            let radius = rng.range(10).abs();
+            let ring = a.ring(radius);
+            assert_eq!(ring.len(), cmp::max(1, 6 * radius as usize));
+            assert!(ring.iter().all(|h| h.distance(a) == radius));
+            assert!(ring.windows(2).all(|w| w[0].distance(w[1]) == 1));
+            let line = a.line_to(b);
+            assert_eq!(line.len(), a.distance(b) as usize + 1);
+            assert_eq!((line[0], line[line.len() - 1]), (a, b));
+            assert!(line.windows(2).all(|w| w[0].distance(w[1]) == 1));
+            assert!(a.neighbours().iter().all(|h| h.distance(a) == 1));
+        }
+    }
+
+    #[test]
+    fn rotations() {
+        let mut rng = Rng(0x94d0_49bb_1331_11eb);
+        for _ in 0..1000 {
+            let a = Hex::new(rng.range(50), rng.range(50));
+            let b = Hex::new(rng.range(50), rng.range(50));
+            let turns = rng.range(12);
+            assert_eq!(a.rotate(turns).length(), a.length());
+            assert_eq!(a.rotate(turns).rotate(-turns), a);
+            assert_eq!(a.rotate(6), a);
+            assert_eq!(b.rotate_around(a, turns).distance(a), b.distance(a));
+            for &d in DIRECTIONS.iter() {
+                assert_eq!(a.neighbour(d).rotate_around(a, turns), a.neighbour(d.turn(turns)));
+            }
+        }
+    }
+}
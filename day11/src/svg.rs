@@ -0,0 +1,127 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use hex::{Direction, Hex};
+use walk::Walk;
+
+// circumradius of a hex in drawing units; the image itself is scaled through the viewBox
+const SIZE: f64 = 10.0;
+// the longest side of the image in pixels, and the most pixels per drawing unit
+const MAX_PIXELS: f64 = 1200.0;
+const MAX_SCALE: f64 = 3.0;
+// above this many hexes in the bounding box, only the visited ones get an outline
+const MAX_GRID: i64 = 20_000;
+
+fn centre(hex: Hex) -> (f64, f64) {
+    let x = SIZE * 1.5 * hex.q as f64;
+    let y = SIZE * 3f64.sqrt() * (hex.r as f64 + hex.q as f64 / 2.0);
+    (x, y)
+}
+
+// a closed subpath around the hex, for use in a `d` attribute
+fn outline(hex: Hex, out: &mut String) {
+    let (cx, cy) = centre(hex);
+    for corner in 0..6 {
+        let angle = (60.0 * corner as f64).to_radians();
+        let command = if corner == 0 { 'M' } else { 'L' };
+        write!(out, "{}{:.1},{:.1}", command, cx + SIZE * angle.cos(), cy + SIZE * angle.sin()).unwrap();
+    }
+    out.push('Z');
+}
+
+fn points(hexes: &[Hex]) -> String {
+    let mut out = String::new();
+    for (i, &hex) in hexes.iter().enumerate() {
+        let (x, y) = centre(hex);
+        if i > 0 {
+            out.push(' ');
+        }
+        write!(out, "{:.1},{:.1}", x, y).unwrap();
+    }
+    out
+}
+
+// the hex itself, with a dot on top that stays visible however far the image is scaled down
+fn marker(out: &mut String, hex: Hex, radius: f64, fill: &str, label: &str) {
+    let mut d = String::new();
+    outline(hex, &mut d);
+    let (x, y) = centre(hex);
+    writeln!(out, "  <g fill=\"{}\"><title>{} ({}, {})</title>", fill, label, hex.q, hex.r).unwrap();
+    writeln!(out, "    <path d=\"{}\"/>", d).unwrap();
+    writeln!(out, "    <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill-opacity=\"0.8\"/>", x, y, radius).unwrap();
+    writeln!(out, "  </g>").unwrap();
+}
+
+// the hexes visited by `moves`, starting from the origin
+fn positions(moves: &[Direction]) -> Vec<Hex> {
+    let mut pos = Hex::default();
+    let mut result = vec![pos];
+    for &direction in moves {
+        pos = pos.neighbour(direction);
+        result.push(pos);
+    }
+    result
+}
+
+// the grid, the walked path, the start, end and furthest hexes and a shortest path back
+pub fn render(moves: &[Direction], walked: &Walk) -> String {
+    let path = positions(moves);
+    let back = positions(&walked.end.path_to(Hex::default()))
+        .into_iter()
+        .map(|h| h + walked.end)
+        .collect::<Vec<_>>();
+
+    let min_q = path.iter().map(|h| h.q).min().unwrap();
+    let max_q = path.iter().map(|h| h.q).max().unwrap();
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+    for &hex in path.iter() {
+        let (x, y) = centre(hex);
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+
+    // every hex whose centre lies in the bounding box if that is small enough
+    let mut grid: BTreeSet<Hex> = path.iter().cloned().collect();
+    let rows = ((max_y - min_y) / (SIZE * 3f64.sqrt())) as i64 + 2;
+    if (max_q - min_q + 1) as i64 * rows <= MAX_GRID {
+        for q in min_q..max_q + 1 {
+            // the row range that covers the box in this column
+            let shift = q as f64 / 2.0;
+            let top = (min_y / (SIZE * 3f64.sqrt()) - shift).floor() as i32;
+            let bottom = (max_y / (SIZE * 3f64.sqrt()) - shift).ceil() as i32;
+            for r in top..bottom + 1 {
+                grid.insert(Hex::new(q, r));
+            }
+        }
+    }
+    let mut grid_path = String::new();
+    for &hex in grid.iter() {
+        outline(hex, &mut grid_path);
+    }
+
+    let margin = 2.0 * SIZE;
+    let (width, height) = (max_x - min_x + 2.0 * margin, max_y - min_y + 2.0 * margin);
+    let scale = (MAX_PIXELS / width.max(height)).min(MAX_SCALE);
+    // lines stay visible when a long walk is scaled down a lot
+    let stroke = (1.5 / scale).max(SIZE / 5.0);
+
+    let mut out = String::new();
+    writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"{:.1} {:.1} {:.1} {:.1}\">",
+             width * scale, height * scale, min_x - margin, min_y - margin, width, height).unwrap();
+    writeln!(out, "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"white\"/>",
+             min_x - margin, min_y - margin, width, height).unwrap();
+    writeln!(out, "  <path d=\"{}\" fill=\"none\" stroke=\"#ccc\" stroke-width=\"{:.2}\"/>",
+             grid_path, stroke / 4.0).unwrap();
+    writeln!(out, "  <polyline points=\"{}\" fill=\"none\" stroke=\"#36c\" stroke-width=\"{:.2}\" stroke-linejoin=\"round\"/>",
+             points(&path), stroke).unwrap();
+    writeln!(out, "  <polyline points=\"{}\" fill=\"none\" stroke=\"#c33\" stroke-width=\"{:.2}\" stroke-dasharray=\"{:.1}\"/>",
+             points(&back), stroke, 4.0 * stroke).unwrap();
+    let radius = (6.0 / scale).max(SIZE / 2.0);
+    marker(&mut out, Hex::default(), radius, "#3c3", "start");
+    marker(&mut out, walked.furthest, radius, "#f90", &format!("furthest, step {}", walked.furthest_step));
+    marker(&mut out, walked.end, radius, "#c33", "end");
+    out.push_str("</svg>\n");
+    out
+}
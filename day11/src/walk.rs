@@ -0,0 +1,103 @@
+use hex::{Direction, Hex};
+
+pub struct Walk {
+    pub end: Hex,
+    pub max_dist: i32,
+    // the number of moves taken when `max_dist` was first reached
+    pub furthest_step: usize,
+    pub furthest: Hex,
+}
+
+pub fn parse_moves(input: &str) -> Vec<Direction> {
+    input
+        .split(',')
+        .map(|direction| Direction::parse(direction).expect("unexpected token in input"))
+        .collect()
+}
+
+pub fn format_moves(moves: &[Direction]) -> String {
+    moves.iter().map(|d| d.name()).collect::<Vec<_>>().join(",")
+}
+
+pub fn walk(moves: &[Direction]) -> Walk {
+    let mut pos = Hex::default();
+    let mut result = Walk {
+        end: pos,
+        max_dist: 0,
+        furthest_step: 0,
+        furthest: pos,
+    };
+    for (i, &direction) in moves.iter().enumerate() {
+        pos = pos.neighbour(direction);
+        if pos.length() > result.max_dist {
+            result.max_dist = pos.length();
+            result.furthest_step = i + 1;
+            result.furthest = pos;
+        }
+    }
+    result.end = pos;
+    result
+}
+
+// drops every pair of opposite moves, keeping the rest in their original order.
+// each move cancels the latest earlier move in the opposite direction that is still left
+pub fn simplify(moves: &[Direction]) -> Vec<Direction> {
+    let mut kept = vec![true; moves.len()];
+    // for every direction, the moves that haven't been cancelled yet
+    let mut open: Vec<Vec<usize>> = vec![Vec::new(); 6];
+    for (i, &direction) in moves.iter().enumerate() {
+        match open[direction.opposite().index()].pop() {
+            Some(j) => {
+                kept[i] = false;
+                kept[j] = false;
+            }
+            None => open[direction.index()].push(i),
+        }
+    }
+    moves
+        .iter()
+        .zip(kept)
+        .filter(|&(_, keep)| keep)
+        .map(|(&d, _)| d)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex::tests::Rng;
+
+    // shortest and simplified paths have to end up in the same place as the walk
+    #[test]
+    fn paths_end_in_the_same_place() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..1000 {
+            let length = (rng.next() % 200) as usize;
+            let moves: Vec<Direction> = (0..length).map(|_| rng.direction()).collect();
+            let end = walk(&moves).end;
+            let shortest = Hex::default().path_to(end);
+            assert_eq!(shortest.len(), end.length() as usize);
+            assert_eq!(walk(&shortest).end, end);
+            let simplified = simplify(&moves);
+            assert_eq!(walk(&simplified).end, end, "moves {}", format_moves(&moves));
+            assert!(simplified.len() <= moves.len());
+            assert!(simplified.iter().all(|d| !simplified.contains(&d.opposite())));
+        }
+    }
+
+    #[test]
+    fn puzzle_examples() {
+        // moves, final distance, furthest distance and the step it was reached at
+        let examples = [
+            ("ne,ne,ne", 3, 3, 3),
+            ("ne,ne,sw,sw", 0, 2, 2),
+            ("ne,ne,s,s", 2, 2, 2),
+            ("se,sw,se,sw,sw", 3, 3, 5),
+        ];
+        for &(input, distance, max_dist, step) in examples.iter() {
+            let walked = walk(&parse_moves(input));
+            assert_eq!((walked.end.length(), walked.max_dist, walked.furthest_step),
+                       (distance, max_dist, step));
+        }
+    }
+}
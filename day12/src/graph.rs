@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use unionfind::Components;
+
+// a pipe whose failure splits its group, with the number of programs left on each side
+pub struct Bridge {
+    pub a: i32,
+    pub b: i32,
+    pub a_side: usize,
+    pub b_side: usize,
+}
+
+// the pipe network as an undirected graph over indices into `programs`.
+// connections only listed from one side still count both ways, and self connections are dropped
+pub struct Graph {
+    programs: Vec<i32>,
+    index: BTreeMap<i32, usize>,
+    adjacent: Vec<Vec<usize>>,
+}
+
+const UNSEEN: usize = usize::MAX;
+
+impl Graph {
+    pub fn new(connections: &BTreeMap<i32, Vec<i32>>) -> Graph {
+        let mut edges: BTreeMap<i32, BTreeSet<i32>> = BTreeMap::new();
+        for (&program, others) in connections.iter() {
+            edges.entry(program).or_default();
+            for &other in others.iter().filter(|&&o| o != program) {
+                edges.entry(program).or_default().insert(other);
+                edges.entry(other).or_default().insert(program);
+            }
+        }
+        let programs: Vec<i32> = edges.keys().cloned().collect();
+        let index: BTreeMap<i32, usize> = programs.iter().enumerate().map(|(i, &p)| (p, i)).collect();
+        let adjacent = edges.values().map(|others| others.iter().map(|o| index[o]).collect()).collect();
+        Graph {
+            programs,
+            index,
+            adjacent,
+        }
+    }
+
+    // distances from `start` to everything it reaches, and the node each was reached from
+    fn bfs(&self, start: usize) -> (Vec<usize>, Vec<usize>) {
+        let mut distance = vec![UNSEEN; self.programs.len()];
+        let mut previous = vec![UNSEEN; self.programs.len()];
+        let mut queue = VecDeque::new();
+        distance[start] = 0;
+        queue.push_back(start);
+        while let Some(node) = queue.pop_front() {
+            for &next in self.adjacent[node].iter() {
+                if distance[next] == UNSEEN {
+                    distance[next] = distance[node] + 1;
+                    previous[next] = node;
+                    queue.push_back(next);
+                }
+            }
+        }
+        (distance, previous)
+    }
+
+    // the programs on a shortest path, both ends included
+    pub fn shortest_path(&self, from: i32, to: i32) -> Option<Vec<i32>> {
+        let (&start, &end) = (self.index.get(&from)?, self.index.get(&to)?);
+        let (distance, previous) = self.bfs(start);
+        if distance[end] == UNSEEN {
+            return None;
+        }
+        let mut path = vec![to];
+        let mut node = end;
+        while node != start {
+            node = previous[node];
+            path.push(self.programs[node]);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    // Tarjan's low-link depth first search, with an explicit stack so long chains of
+    // pipes can't overflow the call stack. returns the bridges and articulation points
+    pub fn cut_edges_and_vertices(&self) -> (Vec<Bridge>, Vec<i32>) {
+        let n = self.programs.len();
+        let mut discovered = vec![UNSEEN; n];
+        let mut low = vec![0; n];
+        let mut parent = vec![UNSEEN; n];
+        // programs in the depth first subtree of each program
+        let mut subtree = vec![1; n];
+        let mut is_cut = vec![false; n];
+        let mut bridges = Vec::new();
+        let mut time = 0;
+
+        for root in 0..n {
+            if discovered[root] != UNSEEN {
+                continue;
+            }
+            let first_bridge = bridges.len();
+            let mut root_children = 0;
+            let mut stack = vec![(root, 0)];
+            discovered[root] = time;
+            low[root] = time;
+            time += 1;
+            while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+                if let Some(&child) = self.adjacent[node].get(*next) {
+                    *next += 1;
+                    if child == parent[node] {
+                        // the edge just came down; there are no parallel pipes to count
+                    } else if discovered[child] == UNSEEN {
+                        parent[child] = node;
+                        discovered[child] = time;
+                        low[child] = time;
+                        time += 1;
+                        stack.push((child, 0));
+                    } else {
+                        low[node] = low[node].min(discovered[child]);
+                    }
+                    continue;
+                }
+                stack.pop();
+                let up = parent[node];
+                if up == UNSEEN {
+                    continue;
+                }
+                low[up] = low[up].min(low[node]);
+                subtree[up] += subtree[node];
+                if low[node] > discovered[up] {
+                    bridges.push(Bridge {
+                        a: self.programs[up],
+                        b: self.programs[node],
+                        a_side: 0,
+                        b_side: subtree[node],
+                    });
+                }
+                if up == root {
+                    root_children += 1;
+                } else if low[node] >= discovered[up] {
+                    is_cut[up] = true;
+                }
+            }
+            is_cut[root] = root_children > 1;
+            // the rest of the group is only known once the whole group has been searched
+            for bridge in bridges[first_bridge..].iter_mut() {
+                bridge.a_side = subtree[root] - bridge.b_side;
+            }
+        }
+
+        let cuts = (0..n).filter(|&i| is_cut[i]).map(|i| self.programs[i]).collect();
+        (bridges, cuts)
+    }
+
+    // the longest shortest path in every component: its length and both ends
+    pub fn diameters(&self, components: &Components) -> Vec<(usize, i32, i32)> {
+        components
+            .all()
+            .iter()
+            .map(|members| {
+                let mut longest = (0, members[0], members[0]);
+                for &program in members.iter() {
+                    let (distance, _) = self.bfs(self.index[&program]);
+                    for &other in members.iter() {
+                        let d = distance[self.index[&other]];
+                        if d > longest.0 {
+                            longest = (d, program, other);
+                        }
+                    }
+                }
+                longest
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse::parse;
+
+    const EXAMPLE: &str = "0 <-> 2\n1 <-> 1\n2 <-> 0, 3, 4\n3 <-> 2, 4\n4 <-> 2, 3, 6\n5 <-> 6\n6 <-> 4, 5\n";
+
+    // a bridge as both ends and the size of each side
+    type Sides = (i32, i32, usize, usize);
+
+    fn cuts(input: &str) -> (Vec<Sides>, Vec<i32>) {
+        let (bridges, points) = Graph::new(&parse(input).connections).cut_edges_and_vertices();
+        let mut bridges: Vec<_> = bridges.iter().map(|b| (b.a, b.b, b.a_side, b.b_side)).collect();
+        bridges.sort();
+        (bridges, points)
+    }
+
+    #[test]
+    fn bridges_and_articulation_points() {
+        assert_eq!(cuts(EXAMPLE), (vec![(0, 2, 1, 5), (4, 6, 4, 2), (6, 5, 5, 1)], vec![2, 4, 6]));
+        // every pipe in a cycle has a way around it
+        assert_eq!(cuts("0 <-> 1, 3\n1 <-> 0, 2\n2 <-> 1, 3\n3 <-> 2, 0\n"), (vec![], vec![]));
+    }
+
+    #[test]
+    fn diameters() {
+        let connections = parse(EXAMPLE).connections;
+        let graph = Graph::new(&connections);
+        // 1 is only connected to itself, so it is a group of its own
+        assert_eq!(graph.diameters(&Components::new(&connections)), vec![(4, 0, 5), (0, 1, 1)]);
+    }
+
+    #[test]
+    fn shortest_paths() {
+        let graph = Graph::new(&parse(EXAMPLE).connections);
+        assert_eq!(graph.shortest_path(0, 5), Some(vec![0, 2, 4, 6, 5]));
+        assert_eq!(graph.shortest_path(1, 1), Some(vec![1]));
+        assert_eq!(graph.shortest_path(0, 1), None);
+        assert_eq!(graph.shortest_path(0, 7), None);
+    }
+}
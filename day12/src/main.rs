@@ -1,67 +1,125 @@
-use std::io::{self, BufRead};
+use std::env;
+use std::io::{self, Read};
 use std::collections::BTreeMap;
-use std::collections::BTreeSet;
 
-fn read_lines() -> BTreeMap<i32, Vec<i32>> {
-    let mut result = BTreeMap::new();
+extern crate disjointset;
 
-    let stdin = io::stdin();
-    for input in stdin.lock().lines() {
-        if let Ok(line) = input {
-            let mut parts = line.split(" <-> ");
-            if let Some(head) = parts.next() {
-                if let Ok(head_number) = head.parse::<i32>() {
-                    let mut connections = Vec::new();
-                    if let Some(tail) = parts.next() {
-                        for connection in tail.split(", ") {
-                            if let Ok(connection_number) = connection.parse::<i32>() {
-                                connections.push(connection_number);
-                            }
-                        }
-                    }
-                    result.insert(head_number, connections);
-                }
-            }
-        }
+mod graph;
+mod parse;
+mod unionfind;
+
+use graph::Graph;
+use unionfind::Components;
+
+// refuses the input if any line can't be used, other problems are left to `check`
+fn read_lines(input: &str) -> Option<BTreeMap<i32, Vec<i32>>> {
+    let pipes = parse::parse(input);
+    let mut ok = true;
+    for problem in pipes.problems.iter().filter(|p| p.is_error()) {
+        println!("Error on {}", problem);
+        ok = false;
+    }
+    if ok {
+        Some(pipes.connections)
+    } else {
+        None
     }
-    result
 }
 
-fn group_size(start: i32, connections: &BTreeMap<i32, Vec<i32>>) -> i32 {
-    let mut stack = vec![start];
-    let mut processed_programs = BTreeSet::new();
-    while let Some(num) = stack.pop() {
-        if let Some(vec) = connections.get(&num) {
-            for connection in vec {
-                if !processed_programs.contains(connection) {
-                    stack.push(*connection);
-                }
-            }
-        }
-        processed_programs.insert(num);
+fn check(input: &str) {
+    let pipes = parse::parse(input);
+    for problem in pipes.problems.iter() {
+        println!("{}", problem);
     }
-    processed_programs.len() as i32
+    let errors = pipes.problems.iter().filter(|p| p.is_error()).count();
+    println!("{} errors, {} other problems", errors, pipes.problems.len() - errors);
 }
 
-fn count_groups(connections: &mut BTreeMap<i32, Vec<i32>>) -> i32 {
-    let mut num_groups = 0;
-    while let Some(&key) = connections.keys().next() {
-        if let Some(mut stack) = connections.remove(&key) {
-            num_groups += 1;
-            while let Some(num) = stack.pop() {
-                if let Some(vec) = connections.remove(&num) {
-                    stack.extend(vec);
+fn join(programs: &[i32]) -> String {
+    programs.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn main() {
+    let mut input = String::new();
+    if io::stdin().read_to_string(&mut input).is_err() {
+        return;
+    }
+    // `--symmetrize` adds the reverse of every one-sided connection before doing anything else
+    let (flags, mut args): (Vec<String>, Vec<String>) = env::args().skip(1).partition(|a| a == "--symmetrize");
+    if args.first().map(|s| s.as_str()) == Some("check") {
+        return check(&input);
+    }
+    let mut connections = match read_lines(&input) {
+        Some(connections) => connections,
+        None => return,
+    };
+    if !flags.is_empty() {
+        let added = parse::symmetrize(&mut connections);
+        eprintln!("added {} missing connections", added);
+    }
+    let components = Components::new(&connections);
+    let mut args = args.drain(..);
+    match args.next().as_deref() {
+        Some("print") => {
+            for (program, others) in connections.iter() {
+                println!("{} <-> {}", program, join(others));
+            }
+        }
+        Some("component") => {
+            let program = args.next().and_then(|s| s.parse::<i32>().ok());
+            match program.and_then(|p| components.component_of(p)) {
+                Some(component) => {
+                    let members = components.members(component);
+                    println!("component {} with {} programs: {}", component, members.len(), join(members));
                 }
+                None => println!("usage: day12 component <program in the input>"),
             }
         }
+        Some("components") => {
+            for (i, members) in components.all().iter().enumerate() {
+                println!("{}: {} programs: {}", i, members.len(), join(members));
+            }
+        }
+        Some("path") => {
+            let ends: Vec<i32> = args.filter_map(|s| s.parse().ok()).collect();
+            if ends.len() != 2 {
+                println!("usage: day12 path <from> <to>");
+                return;
+            }
+            match Graph::new(&connections).shortest_path(ends[0], ends[1]) {
+                Some(path) => println!("{} pipes: {}", path.len() - 1, join(&path)),
+                None => println!("no path from {} to {}", ends[0], ends[1]),
+            }
+        }
+        Some("bridges") => {
+            let (bridges, _) = Graph::new(&connections).cut_edges_and_vertices();
+            for bridge in bridges.iter() {
+                println!("{} <-> {} splits its group into {} and {} programs",
+                         bridge.a, bridge.b, bridge.a_side, bridge.b_side);
+            }
+            println!("{} bridges", bridges.len());
+        }
+        Some("articulation") => {
+            let (_, cuts) = Graph::new(&connections).cut_edges_and_vertices();
+            println!("{} articulation points: {}", cuts.len(), join(&cuts));
+        }
+        Some("diameters") => {
+            let diameters = Graph::new(&connections).diameters(&components);
+            println!("component  size  diameter  between");
+            for (i, &(length, from, to)) in diameters.iter().enumerate() {
+                println!("{:>9}  {:>4}  {:>8}  {} and {}", i, components.members(i).len(), length, from, to);
+            }
+        }
+        Some("histogram") => {
+            println!("size  components");
+            for (size, count) in components.size_histogram() {
+                println!("{:>4}  {}", size, count);
+            }
+        }
+        _ => {
+            let size = components.component_of(0).map_or(0, |c| components.members(c).len());
+            println!("number of programs in group containing 0: {}", size);
+            println!("{}", components.count());
+        }
     }
-    num_groups
-}
-
-
-fn main() {
-    let mut connections = read_lines();
-    let size = group_size(0, &connections);
-    println!("number of programs in group containing 0: {}", size);
-    println!("{}", count_groups(&mut connections));
 }
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fmt;
+
+// errors stop the input from being used at all, the rest are inconsistencies
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    Malformed { line: usize, reason: String },
+    DuplicateDefinition { line: usize, program: i32, first_line: usize },
+    DuplicateConnection { line: usize, program: i32, other: i32 },
+    SelfConnection { line: usize, program: i32 },
+    // `program` lists `other`, but `other` doesn't list it back or isn't defined at all
+    Asymmetric { line: usize, program: i32, other: i32, other_defined: bool },
+}
+
+impl Problem {
+    pub fn is_error(&self) -> bool {
+        matches!(*self, Problem::Malformed { .. } | Problem::DuplicateDefinition { .. })
+    }
+
+    pub fn line(&self) -> usize {
+        match *self {
+            Problem::Malformed { line, .. } |
+            Problem::DuplicateDefinition { line, .. } |
+            Problem::DuplicateConnection { line, .. } |
+            Problem::SelfConnection { line, .. } |
+            Problem::Asymmetric { line, .. } => line,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Problem::Malformed { line, ref reason } => write!(f, "line {}: {}", line, reason),
+            Problem::DuplicateDefinition { line, program, first_line } => {
+                write!(f, "line {}: program {} was already defined on line {}", line, program, first_line)
+            }
+            Problem::DuplicateConnection { line, program, other } => {
+                write!(f, "line {}: program {} lists {} more than once", line, program, other)
+            }
+            Problem::SelfConnection { line, program } => {
+                write!(f, "line {}: program {} is connected to itself", line, program)
+            }
+            Problem::Asymmetric { line, program, other, other_defined: true } => {
+                write!(f, "line {}: program {} lists {}, but {} doesn't list {}", line, program, other, other, program)
+            }
+            Problem::Asymmetric { line, program, other, other_defined: false } => {
+                write!(f, "line {}: program {} lists {}, which is never defined", line, program, other)
+            }
+        }
+    }
+}
+
+pub struct Pipes {
+    pub connections: BTreeMap<i32, Vec<i32>>,
+    // line each program was defined on
+    pub lines: BTreeMap<i32, usize>,
+    pub problems: Vec<Problem>,
+}
+
+fn program(text: &str, line: usize) -> Result<i32, Problem> {
+    text.trim().parse::<i32>().map_err(|_| {
+        Problem::Malformed {
+            line,
+            reason: format!("'{}' is not a program number", text.trim()),
+        }
+    })
+}
+
+fn parse_line(text: &str, line: usize) -> Result<(i32, Vec<i32>), Problem> {
+    let mut parts = text.splitn(2, "<->");
+    let head = parts.next().unwrap();
+    let tail = parts.next().ok_or_else(|| {
+        Problem::Malformed {
+            line,
+            reason: "expected '<program> <-> <programs>'".to_owned(),
+        }
+    })?;
+    let head = program(head, line)?;
+    if tail.trim().is_empty() {
+        return Err(Problem::Malformed {
+            line,
+            reason: format!("program {} has no connections", head),
+        });
+    }
+    let others = tail.split(',').map(|p| program(p, line)).collect::<Result<_, _>>()?;
+    Ok((head, others))
+}
+
+// reads every line it can, recording a problem for everything that doesn't fit
+pub fn parse(input: &str) -> Pipes {
+    let mut pipes = Pipes {
+        connections: BTreeMap::new(),
+        lines: BTreeMap::new(),
+        problems: Vec::new(),
+    };
+    for (i, text) in input.lines().enumerate() {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let line = i + 1;
+        let (head, mut others) = match parse_line(text, line) {
+            Ok(parsed) => parsed,
+            Err(problem) => {
+                // the program is still defined here, its connections just can't be read
+                let head = text.split("<->").next().and_then(|h| h.trim().parse::<i32>().ok());
+                if let Some(head) = head {
+                    pipes.lines.entry(head).or_insert(line);
+                }
+                pipes.problems.push(problem);
+                continue;
+            }
+        };
+        match pipes.lines.entry(head) {
+            Entry::Occupied(first) => {
+                pipes.problems.push(Problem::DuplicateDefinition {
+                    line,
+                    program: head,
+                    first_line: *first.get(),
+                });
+                continue;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(line);
+            }
+        }
+        let mut seen = Vec::with_capacity(others.len());
+        others.retain(|&other| {
+            if seen.contains(&other) {
+                pipes.problems.push(Problem::DuplicateConnection { line, program: head, other });
+                false
+            } else {
+                seen.push(other);
+                true
+            }
+        });
+        pipes.connections.insert(head, others);
+    }
+    pipes.problems.extend(check(&pipes.connections, &pipes.lines));
+    pipes.problems.sort_by_key(|p| p.line());
+    pipes
+}
+
+fn check(connections: &BTreeMap<i32, Vec<i32>>, lines: &BTreeMap<i32, usize>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (&program, others) in connections.iter() {
+        let line = lines[&program];
+        for &other in others {
+            if other == program {
+                problems.push(Problem::SelfConnection { line, program });
+                continue;
+            }
+            let back = connections.get(&other);
+            // defined on a malformed line, so there's no telling what it lists
+            if back.is_none() && lines.contains_key(&other) {
+                continue;
+            }
+            if !back.is_some_and(|b| b.contains(&program)) {
+                problems.push(Problem::Asymmetric {
+                    line,
+                    program,
+                    other,
+                    other_defined: back.is_some(),
+                });
+            }
+        }
+    }
+    problems
+}
+
+// adds every missing reverse connection, defining programs that were only listed.
+// returns the number of connections added
+pub fn symmetrize(connections: &mut BTreeMap<i32, Vec<i32>>) -> usize {
+    let mut missing = Vec::new();
+    for (&program, others) in connections.iter() {
+        for &other in others {
+            if !connections.get(&other).is_some_and(|b| b.contains(&program)) {
+                missing.push((other, program));
+            }
+        }
+    }
+    for &(program, other) in missing.iter() {
+        connections.entry(program).or_default().push(other);
+    }
+    missing.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(input: &str) -> Vec<Problem> {
+        parse(input).problems
+    }
+
+    fn malformed(line: usize, reason: &str) -> Problem {
+        Problem::Malformed { line, reason: reason.to_owned() }
+    }
+
+    #[test]
+    fn malformed_lines() {
+        assert_eq!(problems("0 - 1\n"), vec![malformed(1, "expected '<program> <-> <programs>'")]);
+        assert_eq!(problems("0 <->\n"), vec![malformed(1, "program 0 has no connections")]);
+        assert_eq!(problems("x <-> 1\n1 <-> 2, y\n"),
+                   vec![malformed(1, "'x' is not a program number"),
+                        malformed(2, "'y' is not a program number")]);
+    }
+
+    #[test]
+    fn malformed_lines_still_define_their_program() {
+        assert_eq!(problems("0 <-> 2, x\n2 <-> 0\n0 <-> 5\n"),
+                   vec![malformed(1, "'x' is not a program number"),
+                        Problem::DuplicateDefinition { line: 3, program: 0, first_line: 1 }]);
+    }
+
+    #[test]
+    fn duplicates() {
+        assert_eq!(problems("0 <-> 1\n1 <-> 0\n0 <-> 1\n"),
+                   vec![Problem::DuplicateDefinition { line: 3, program: 0, first_line: 1 }]);
+        let pipes = parse("0 <-> 1, 2, 1\n1 <-> 0\n2 <-> 0\n");
+        assert_eq!(pipes.problems, vec![Problem::DuplicateConnection { line: 1, program: 0, other: 1 }]);
+        assert_eq!(pipes.connections[&0], vec![1, 2]);
+    }
+
+    #[test]
+    fn self_connection() {
+        assert_eq!(problems("0 <-> 0, 1\n1 <-> 0\n"),
+                   vec![Problem::SelfConnection { line: 1, program: 0 }]);
+    }
+
+    #[test]
+    fn asymmetric() {
+        assert_eq!(problems("0 <-> 1\n1 <-> 2\n2 <-> 1\n"),
+                   vec![Problem::Asymmetric { line: 1, program: 0, other: 1, other_defined: true }]);
+        assert_eq!(problems("0 <-> 3\n"),
+                   vec![Problem::Asymmetric { line: 1, program: 0, other: 3, other_defined: false }]);
+        assert!(problems("0 <-> 2\n1 <-> 3\n2 <-> 0\n3 <-> 1\n").is_empty());
+    }
+
+    #[test]
+    fn symmetrize_counts_added_connections() {
+        let mut connections = parse("0 <-> 1, 2\n1 <-> 3\n").connections;
+        assert_eq!(symmetrize(&mut connections), 3);
+        let expected: BTreeMap<i32, Vec<i32>> =
+            vec![(0, vec![1, 2]), (1, vec![3, 0]), (2, vec![0]), (3, vec![1])].into_iter().collect();
+        assert_eq!(connections, expected);
+        assert_eq!(symmetrize(&mut connections), 0);
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use disjointset::DisjointSet;
+
+// the groups of a pipe network. components are numbered in order of their lowest program
+pub struct Components {
+    // every program, sorted, with its component
+    component: BTreeMap<i32, usize>,
+    members: Vec<Vec<i32>>,
+}
+
+impl Components {
+    // programs that are only mentioned as a connection count as well
+    pub fn new(connections: &BTreeMap<i32, Vec<i32>>) -> Components {
+        let mut index: BTreeMap<i32, usize> = BTreeMap::new();
+        for (&program, others) in connections.iter() {
+            for &p in Some(&program).into_iter().chain(others.iter()) {
+                let next = index.len();
+                index.entry(p).or_insert(next);
+            }
+        }
+        let mut sets = DisjointSet::new(index.len());
+        for (program, others) in connections.iter() {
+            for other in others {
+                sets.union(index[program], index[other]);
+            }
+        }
+
+        let mut numbers: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut members: Vec<Vec<i32>> = Vec::with_capacity(sets.count());
+        let mut component = BTreeMap::new();
+        // programs come out of the map in order, so members end up sorted
+        for (&program, &i) in index.iter() {
+            let root = sets.find(i);
+            let next = numbers.len();
+            let number = *numbers.entry(root).or_insert(next);
+            if number == members.len() {
+                members.push(Vec::new());
+            }
+            members[number].push(program);
+            component.insert(program, number);
+        }
+        Components { component, members }
+    }
+
+    pub fn count(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn component_of(&self, program: i32) -> Option<usize> {
+        self.component.get(&program).cloned()
+    }
+
+    // the sorted programs in a component
+    pub fn members(&self, component: usize) -> &[i32] {
+        &self.members[component]
+    }
+
+    pub fn all(&self) -> &[Vec<i32>] {
+        &self.members
+    }
+
+    // how many components there are of each size
+    pub fn size_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for members in self.members.iter() {
+            *histogram.entry(members.len()).or_insert(0) += 1;
+        }
+        histogram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components() {
+        // 7 and 8 are only ever listed as a connection
+        let connections: BTreeMap<i32, Vec<i32>> = vec![(0, vec![2]),
+                                                        (1, vec![1]),
+                                                        (2, vec![0, 3, 4]),
+                                                        (3, vec![2, 4]),
+                                                        (4, vec![2, 3, 6]),
+                                                        (5, vec![6]),
+                                                        (6, vec![4, 5]),
+                                                        (9, vec![7]),
+                                                        (12, vec![8])]
+            .into_iter()
+            .collect();
+        let before = connections.clone();
+        let components = Components::new(&connections);
+        assert_eq!(connections, before);
+
+        assert_eq!(components.count(), 4);
+        assert_eq!(components.all(), &[vec![0, 2, 3, 4, 5, 6], vec![1], vec![7, 9], vec![8, 12]]);
+        assert_eq!(components.members(2), &[7, 9]);
+        assert_eq!(components.component_of(8), Some(3));
+        assert_eq!(components.component_of(6), Some(0));
+        assert_eq!(components.component_of(10), None);
+        let histogram: Vec<_> = components.size_histogram().into_iter().collect();
+        assert_eq!(histogram, vec![(1, 1), (2, 2), (6, 1)]);
+    }
+}
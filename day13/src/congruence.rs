@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::Layer;
+
+// the wheel of allowed residues is only extended while it stays below this size;
+// the remaining layers are sieved out of the candidates it leaves
+const WHEEL_LIMIT: usize = 1 << 20;
+
+// delays sieved against the remaining layers at a time
+const SEGMENT: u64 = 1 << 16;
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let tmp = a % b;
+        a = b;
+        b = tmp;
+    }
+    a
+}
+
+// a scanner with range `r` is at the top every 2(r - 1) picoseconds, and the packet reaches
+// depth `d` at `delay + d`, so it is caught when delay ≡ -d (mod 2(r - 1)).
+// returns the forbidden residues for every period, or None if some layer always catches
+fn forbidden(layers: &[Layer]) -> Option<BTreeMap<u64, BTreeSet<u64>>> {
+    let mut result: BTreeMap<u64, BTreeSet<u64>> = BTreeMap::new();
+    for (depth, layer) in layers.iter().enumerate() {
+        match layer.range {
+            r if r <= 0 => (),
+            // never leaves the top
+            1 => return None,
+            r => {
+                let period = 2 * (r as u64 - 1);
+                let residue = (period - depth as u64 % period) % period;
+                result.entry(period).or_default().insert(residue);
+            }
+        }
+    }
+    Some(result)
+}
+
+// the smallest delay that gets through without being caught, found by sieving residues:
+// the allowed delays modulo the lcm of the smaller periods are enumerated once, and
+// the candidates they leave are sieved against the larger periods a segment at a time
+pub fn minimum_delay(layers: &[Layer]) -> Option<u64> {
+    let forbidden = forbidden(layers)?;
+    // layers that between them catch every residue of their period can't be passed
+    if forbidden.iter().any(|(&period, residues)| residues.len() as u64 == period) {
+        return None;
+    }
+
+    // delays repeat with the lcm of all periods; if none below it works, none ever will
+    let mut bound: u64 = 1;
+    for &period in forbidden.keys() {
+        let lcm = (bound / gcd(bound, period)) as u128 * period as u128;
+        bound = lcm.min(u64::MAX as u128) as u64;
+    }
+
+    let mut wheel: Vec<u64> = vec![0];
+    let mut modulus: u64 = 1;
+    let mut remaining = Vec::new();
+    for (&period, residues) in forbidden.iter() {
+        let lcm = modulus / gcd(modulus, period) * period;
+        let grown = (lcm / modulus) as usize * wheel.len();
+        if !remaining.is_empty() || grown > WHEEL_LIMIT {
+            remaining.push((period, residues));
+            continue;
+        }
+        let mut next = Vec::with_capacity(grown);
+        for k in 0..lcm / modulus {
+            for &r in wheel.iter() {
+                let delay = k * modulus + r;
+                if !residues.contains(&(delay % period)) {
+                    next.push(delay);
+                }
+            }
+        }
+        if next.is_empty() {
+            return None;
+        }
+        wheel = next;
+        modulus = lcm;
+    }
+
+    // a larger period can rule out whole residue classes of the wheel: the delays in a class
+    // modulo gcd(modulus, period) meet every residue of the period in that class
+    for &(period, residues) in remaining.iter() {
+        let g = gcd(modulus, period);
+        let per_class = period / g;
+        if (residues.len() as u64) < per_class {
+            continue;
+        }
+        let mut counts: BTreeMap<u64, u64> = BTreeMap::new();
+        for &r in residues.iter() {
+            *counts.entry(r % g).or_default() += 1;
+        }
+        wheel.retain(|&w| counts.get(&(w % g)) != Some(&per_class));
+        if wheel.is_empty() {
+            return None;
+        }
+    }
+
+    // candidates are visited in order as a number of turns of the wheel and an index into it
+    let mut turn: u64 = 0;
+    let mut index = 0;
+    let mut blocked = vec![false; SEGMENT as usize];
+    loop {
+        let low = turn.checked_add(wheel[index])?;
+        if low >= bound {
+            return None;
+        }
+        let high = low.saturating_add(SEGMENT).min(bound);
+        for b in blocked.iter_mut() {
+            *b = false;
+        }
+        for &(period, residues) in remaining.iter() {
+            for &r in residues.iter() {
+                // offsets into the segment, so nothing overflows near the bound
+                let mut offset = (r + period - low % period) % period;
+                while offset < high - low {
+                    blocked[offset as usize] = true;
+                    offset += period;
+                }
+            }
+        }
+        loop {
+            let delay = turn.checked_add(wheel[index])?;
+            if delay >= high {
+                break;
+            }
+            if !blocked[(delay - low) as usize] {
+                return Some(delay);
+            }
+            index += 1;
+            if index == wheel.len() {
+                index = 0;
+                turn = turn.checked_add(modulus)?;
+            }
+        }
+    }
+}
+
+// what happens to a packet leaving after a given delay
+#[derive(Debug, PartialEq)]
+pub struct Trip {
+    // depths of the layers whose scanner is at the top when the packet gets there
+    pub caught: Vec<usize>,
+    // the sum of depth * range over those layers
+    pub severity: u64,
+}
+
+fn catches(layer: &Layer, depth: usize, delay: u64) -> bool {
+    match layer.range {
+        r if r <= 0 => false,
+        1 => true,
+        r => (delay + depth as u64).is_multiple_of(2 * (r as u64 - 1)),
+    }
+}
+
+pub fn trip(layers: &[Layer], delay: u64) -> Trip {
+    let caught: Vec<usize> = (0..layers.len()).filter(|&d| catches(&layers[d], d, delay)).collect();
+    let severity = caught.iter().map(|&d| d as u64 * layers[d].range as u64).sum();
+    Trip { caught, severity }
+}
+
+// whether any layer catches a packet leaving after `delay`, without simulating the scanners
+#[cfg(test)]
+pub fn is_caught_at(layers: &[Layer], delay: u64) -> bool {
+    layers.iter().enumerate().any(|(depth, layer)| catches(layer, depth, delay))
+}
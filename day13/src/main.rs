@@ -1,5 +1,7 @@
+use std::env;
 use std::io::{self, BufRead};
-use std::collections::BTreeSet;
+
+mod congruence;
 
 #[derive(Debug, Clone)]
 struct Layer {
@@ -11,18 +13,16 @@ struct Layer {
 fn read_input() -> Vec<Layer> {
     let stdin = io::stdin();
     let mut result = Vec::new();
-    for input in stdin.lock().lines() {
-        if let Ok(line) = input {
-            let mut parts = line.split(": ");
-            if let Some(depth) = parts.next() {
-                if let Some(range) = parts.next() {
-                    if let Ok(depth_num) = depth.parse::<usize>() {
-                        if let Ok(range_num) = range.parse::<i32>() {
-                            while result.len() <= depth_num {
-                                result.push(Layer{range: 0, position: 0, direction: 1});
-                            }
-                            result[depth_num].range = range_num;
+    for line in stdin.lock().lines().map_while(Result::ok) {
+        let mut parts = line.split(": ");
+        if let Some(depth) = parts.next() {
+            if let Some(range) = parts.next() {
+                if let Ok(depth_num) = depth.parse::<usize>() {
+                    if let Ok(range_num) = range.parse::<i32>() {
+                        while result.len() <= depth_num {
+                            result.push(Layer{range: 0, position: 0, direction: 1});
                         }
+                        result[depth_num].range = range_num;
                     }
                 }
             }
@@ -31,7 +31,7 @@ fn read_input() -> Vec<Layer> {
     result
 }
 
-fn step_layers(layers: &mut Vec<Layer>) {
+fn step_layers(layers: &mut [Layer]) {
     for layer in layers.iter_mut() {
         if layer.range > 0 {
             layer.position += layer.direction;
@@ -54,63 +54,205 @@ fn walk_severity(mut layers: Vec<Layer>) -> i32 {
     severity
 }
 
-fn is_caught(mut layers: Vec<Layer>) -> bool {
-    for packet_position in 0..layers.len() {
-        if layers[packet_position].range > 0 && layers[packet_position].position == 0 {
-            return true;
-        }
-        step_layers(&mut layers);
+// why a packet leaving after `delay` gets caught
+fn report(layers: &[Layer], delay: u64) {
+    let trip = congruence::trip(layers, delay);
+    println!("depth  range  arrives  severity");
+    for &depth in trip.caught.iter() {
+        let range = layers[depth].range;
+        println!("{:>5}  {:>5}  {:>7}  {:>8}", depth, range, delay + depth as u64, depth as u64 * range as u64);
     }
-    false
+    println!("caught by {} layers, severity {}", trip.caught.len(), trip.severity);
 }
 
-fn layer_period(layers: &Vec<Layer>) -> i32 {
-    let mut layer_ranges = BTreeSet::new();
-    for layer in layers.iter() {
-        if layer.range > 0 {
-            // the period of one range is 2*(range - 1)
-            // i.e. the number of steps to return to the starting position
-            layer_ranges.insert(2 * (layer.range - 1));
-        }
+fn sweep(layers: &[Layer], delays: u64) {
+    println!("delay,severity,caught");
+    for delay in 0..delays {
+        let trip = congruence::trip(layers, delay);
+        println!("{},{},{}", delay, trip.severity, trip.caught.len());
     }
-    fn gcd(mut a: i32, b: &i32) -> i32 {
-        let mut b = *b;
-        while b != 0 {
-            let tmp = a % b;
-            a = b;
-            b = tmp;
-        }
-        a
-    }
-    let lcm = |a, b| {
-        let res = (a * b) / gcd(a, b);
-        res
-    };
-    // get lowest common multiple of all
-    layer_ranges.iter().fold(1, lcm)
 }
 
-fn minimum_wait(mut layers: Vec<Layer>) -> Option<i32> {
-    // after the period, the layers should all be back to their starting position
-    let period = layer_period(&layers);
-    for i in 0..period {
-        let caught = is_caught(layers.clone());
-        if !caught {
-            return Some(i);
-        }
-        step_layers(&mut layers);
-    }
-    None
-}
-
-
 fn main() {
+    let mut args = env::args().skip(1);
+    let mode = args.next();
+    let number = args.next().and_then(|s| s.parse::<u64>().ok());
+    match mode.as_deref() {
+        Some("report") => return report(&read_input(), number.unwrap_or(0)),
+        Some("sweep") => return sweep(&read_input(), number.unwrap_or(100)),
+        _ => (),
+    }
     let layers = read_input();
     let severity = walk_severity(layers.clone());
     println!("severity when starting at picosecond 0: {}", severity);
-    if let Some(steps) = minimum_wait(layers) {
+    if let Some(steps) = congruence::minimum_delay(&layers) {
         println!("Not caught after waiting {} steps", steps);
     } else {
         println!("No way to not get caught");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    // the original simulation, kept to check the solver against
+    fn is_caught(mut layers: Vec<Layer>) -> bool {
+        for packet_position in 0..layers.len() {
+            if layers[packet_position].range > 0 && layers[packet_position].position == 0 {
+                return true;
+            }
+            step_layers(&mut layers);
+        }
+        false
+    }
+
+    fn layer_period(layers: &[Layer]) -> i32 {
+        let mut layer_ranges = BTreeSet::new();
+        for layer in layers.iter() {
+            if layer.range > 0 {
+                // the period of one range is 2*(range - 1)
+                // i.e. the number of steps to return to the starting position
+                layer_ranges.insert(2 * (layer.range - 1));
+            }
+        }
+        fn gcd(mut a: i32, b: &i32) -> i32 {
+            let mut b = *b;
+            while b != 0 {
+                let tmp = a % b;
+                a = b;
+                b = tmp;
+            }
+            a
+        }
+        let lcm = |a, b| (a * b) / gcd(a, b);
+        // get lowest common multiple of all
+        layer_ranges.iter().fold(1, lcm)
+    }
+
+    fn minimum_wait(mut layers: Vec<Layer>) -> Option<i32> {
+        // after the period, the layers should all be back to their starting position
+        let period = layer_period(&layers);
+        for i in 0..period {
+            let caught = is_caught(layers.clone());
+            if !caught {
+                return Some(i);
+            }
+            step_layers(&mut layers);
+        }
+        None
+    }
+
+    // xorshift64
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, limit: u64) -> u64 {
+            self.next() % limit
+        }
+    }
+
+    // a firewall with `depth` layers, some of them empty, and ranges up to `max_range`
+    fn random_firewall(rng: &mut Rng, depth: usize, max_range: u64) -> Vec<Layer> {
+        (0..depth)
+            .map(|_| {
+                let range = if rng.below(3) == 0 { 0 } else { rng.below(max_range) as i32 + 1 };
+                Layer { range, position: 0, direction: 1 }
+            })
+            .collect()
+    }
+
+    // a firewall from `depth: range` pairs, the way the input lists them
+    fn firewall(ranges: &[(usize, i32)]) -> Vec<Layer> {
+        let mut layers = Vec::new();
+        for &(depth, range) in ranges {
+            while layers.len() <= depth {
+                layers.push(Layer { range: 0, position: 0, direction: 1 });
+            }
+            layers[depth].range = range;
+        }
+        layers
+    }
+
+    #[test]
+    fn puzzle_example() {
+        let layers = firewall(&[(0, 3), (1, 2), (4, 4), (6, 4)]);
+        assert_eq!(walk_severity(layers.clone()), 24);
+        assert_eq!(congruence::minimum_delay(&layers), Some(10));
+        assert_eq!(congruence::trip(&layers, 0).caught, [0, 6]);
+    }
+
+    // the solver has to agree with the simulator on small firewalls
+    #[test]
+    fn matches_simulator() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..2_000 {
+            let depth = rng.below(12) as usize + 1;
+            let layers = random_firewall(&mut rng, depth, 9);
+            let ranges: Vec<i32> = layers.iter().map(|l| l.range).collect();
+            let simulated = minimum_wait(layers.clone()).map(|d| d as u64);
+            assert_eq!(congruence::minimum_delay(&layers), simulated, "ranges {:?}", ranges);
+            // the simulator lets a scanner with range 1 wander off, so those can't be compared
+            if ranges.contains(&1) {
+                continue;
+            }
+            let delay = rng.below(50);
+            let mut delayed = layers.clone();
+            for _ in 0..delay {
+                step_layers(&mut delayed);
+            }
+            let trip = congruence::trip(&layers, delay);
+            assert_eq!(trip.severity, walk_severity(delayed.clone()) as u64, "ranges {:?}", ranges);
+            assert_eq!(trip.caught.is_empty(), !is_caught(delayed), "ranges {:?}", ranges);
+        }
+    }
+
+    // answers on large firewalls have to be safe delays smaller than any other safe delay
+    #[test]
+    fn large_firewalls() {
+        let mut rng = Rng(0xd1b5_4a32_d192_ed03);
+        for _ in 0..10 {
+            let depth = rng.below(400) as usize + 100;
+            // even ranges keep the periods from sharing every residue, so about half of these can be passed
+            let layers: Vec<Layer> = random_firewall(&mut rng, depth, 80)
+                .into_iter()
+                .map(|l| Layer { range: if l.range > 0 { l.range / 2 * 2 + 2 } else { 0 }, ..l })
+                .collect();
+            match congruence::minimum_delay(&layers) {
+                Some(delay) => {
+                    assert!(!congruence::is_caught_at(&layers, delay));
+                    if delay < 100_000 {
+                        assert!((0..delay).all(|d| congruence::is_caught_at(&layers, d)));
+                    }
+                }
+                None => assert!((0..10_000).all(|d| congruence::is_caught_at(&layers, d))),
+            }
+        }
+    }
+
+    // scanners that between them are at the top at every moment of their period used to
+    // send the solver through every delay up to the lcm of all the periods
+    #[test]
+    fn impassable_firewalls() {
+        let mut ranges: Vec<(usize, i32)> = (0..200).map(|d| (d, 101)).collect();
+        ranges.extend([4, 6, 8, 12, 14, 18, 20, 24].iter().enumerate().map(|(i, &r)| (200 + i, r)));
+        assert_eq!(congruence::minimum_delay(&firewall(&ranges)), None);
+
+        // the layers of period 200 only catch odd delays and the one at depth 0 catches even
+        // ones. the small periods fill the wheel first, so the layers of period 200 are only
+        // reached when sieving, where they rule out the rest of the wheel
+        let mut ranges: Vec<(usize, i32)> = (1..200).step_by(2).map(|d| (d, 101)).collect();
+        ranges.push((0, 2));
+        ranges.extend([4, 6, 8, 12, 14, 18, 20, 24].iter().enumerate().map(|(i, &r)| (200 + i, r)));
+        assert_eq!(congruence::minimum_delay(&firewall(&ranges)), None);
+    }
+}
@@ -0,0 +1,137 @@
+use std::thread;
+
+use knothash;
+
+use super::hash_to_bitvec;
+
+// the disk, one 128 bit row per hash; the leftmost square is the highest bit
+pub struct Grid {
+    pub rows: Vec<u128>,
+}
+
+fn hash_row(key: &str, row: usize) -> u128 {
+    let digest = knothash::hash(format!("{}-{}", key, row).as_bytes());
+    u128::from_be_bytes(digest.0)
+}
+
+// extends row `i` of a region with whatever it reaches from the rows next to it
+fn grow(region: &mut [u128], rows: &[u128], i: usize) -> bool {
+    let mut reach = region[i];
+    if i > 0 {
+        reach |= region[i - 1] & rows[i];
+    }
+    if i + 1 < region.len() {
+        reach |= region[i + 1] & rows[i];
+    }
+    let grown = spread(reach, rows[i]);
+    let changed = grown != region[i];
+    region[i] = grown;
+    changed
+}
+
+// the squares of `region` can spread to within `row`, left or right
+fn spread(mut region: u128, row: u128) -> u128 {
+    loop {
+        let grown = (region | region << 1 | region >> 1) & row;
+        if grown == region {
+            return region;
+        }
+        region = grown;
+    }
+}
+
+impl Grid {
+    // hashes the rows on as many threads as there are cores, each taking a run of rows
+    pub fn hash(key: &str, height: usize) -> Grid {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk = height.div_ceil(threads).max(1);
+        let mut rows = vec![0; height];
+        thread::scope(|scope| {
+            for (n, part) in rows.chunks_mut(chunk).enumerate() {
+                scope.spawn(move || {
+                    for (i, row) in part.iter_mut().enumerate() {
+                        *row = hash_row(key, n * chunk + i);
+                    }
+                });
+            }
+        });
+        Grid { rows }
+    }
+
+    pub fn used(&self) -> u32 {
+        self.rows.iter().map(|row| row.count_ones()).sum()
+    }
+
+    // floods one region at a time, a whole row at once: a region spreads sideways within
+    // a row, and up and down wherever the neighbouring row is used underneath it
+    pub fn count_regions(&self) -> usize {
+        let mut left = self.rows.clone();
+        let mut count = 0;
+        // rows above `start` are all free, so a region found there only reaches downwards
+        let mut start = 0;
+        while start < left.len() {
+            if left[start] == 0 {
+                start += 1;
+                continue;
+            }
+            // the region in rows `start..start + region.len()`
+            let mut region = vec![spread(1 << left[start].trailing_zeros(), left[start])];
+            // sweep down, adding rows as the region reaches them, and back up until
+            // nothing grows any more
+            let mut changed = true;
+            while changed {
+                changed = false;
+                let mut i = 0;
+                while i < region.len() {
+                    changed |= grow(&mut region, &left[start..], i);
+                    if i + 1 == region.len() && start + i + 1 < left.len() {
+                        let below = spread(region[i] & left[start + i + 1], left[start + i + 1]);
+                        if below != 0 {
+                            region.push(below);
+                            changed = true;
+                        }
+                    }
+                    i += 1;
+                }
+                for i in (0..region.len()).rev() {
+                    changed |= grow(&mut region, &left[start..], i);
+                }
+            }
+            for (row, taken) in left[start..].iter_mut().zip(region) {
+                *row &= !taken;
+            }
+            count += 1;
+        }
+        count
+    }
+
+    pub fn to_bools(&self) -> Vec<Vec<bool>> {
+        self.rows.iter().map(|row| hash_to_bitvec(&row.to_be_bytes())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regions;
+
+    #[test]
+    fn puzzle_example() {
+        let grid = Grid::hash("flqrgnkx", 128);
+        assert_eq!(grid.used(), 8108);
+        assert_eq!(grid.count_regions(), 1242);
+    }
+
+    // the bitwise grid has to agree with the square by square labeling
+    #[test]
+    fn matches_labeling() {
+        for n in 0..20 {
+            let key = format!("flqrgnkx{}", n);
+            let grid = Grid::hash(&key, 64 + 8 * n);
+            let squares = grid.to_bools();
+            let used = squares.iter().flat_map(|row| row.iter()).filter(|&&s| s).count();
+            assert_eq!(grid.used() as usize, used, "key {}", key);
+            assert_eq!(grid.count_regions(), regions::label(&squares).regions.len(), "key {}", key);
+        }
+    }
+}
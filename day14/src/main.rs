@@ -1,37 +1,15 @@
-fn reverse<T>(list: &mut [T], mut start: usize, mut len: usize) {
-    while len > 1 {
-        start = start % list.len();
-        let endpos = (start + len - 1) % list.len();
-        list.swap(start, endpos);
-        start = start + 1;
-        len -= 2;
-    }
-}
+use std::env;
+use std::io::{self, BufRead};
 
-fn knothash(list: &mut [u32], lengths: &[usize]) {
-    let mut skipsize = 0;
-    let mut pos = 0;
-    for _ in 0..64 {
-        for &length in lengths {
-            reverse(list, pos, length);
-            pos = (pos + length + skipsize) % list.len();
-            skipsize += 1;
-        }
-    }
-}
+extern crate knothash;
+extern crate disjointset;
 
-fn hash(line: String) -> Vec<u32> {
-    let mut result = Vec::new();
-    for byte in line.bytes() {
-        result.push(byte as usize);
-    }
-    result.extend(&[17, 31, 73, 47, 23]);
-    let mut arr = (0..256).collect::<Vec<_>>();
-    knothash(&mut arr, &result);
-    arr.chunks(16).map(|subslice| subslice.iter().fold(0, |a, &b| a ^ b)).collect::<Vec<_>>()
-}
+mod grid;
+mod regions;
+
+use grid::Grid;
 
-fn hash_to_bitvec(vals: &Vec<u32>) -> Vec<bool> {
+fn hash_to_bitvec(vals: &[u8]) -> Vec<bool> {
     let mut res = Vec::new();
     for val in vals {
         for i in (0..8).rev() {
@@ -41,52 +19,53 @@ fn hash_to_bitvec(vals: &Vec<u32>) -> Vec<bool> {
     res
 }
 
-fn mark_region(vals: &mut Vec<Vec<bool>>, i: usize, j: usize) {
-    let mut stack = vec![(i, j)];
-    while let Some((i, j)) = stack.pop() {
-        if vals.len() > i + 1 && vals[i + 1][j] {
-            stack.push((i + 1, j));
-        }
-        if i > 0 && vals[i - 1][j] {
-            stack.push((i - 1, j));
-        }
-        if vals[i].len() > j + 1 && vals[i][j + 1] {
-            stack.push((i, j + 1));
+// one line per key read from stdin: the key, the squares used and the number of regions
+fn bulk(height: usize) {
+    let stdin = io::stdin();
+    for key in stdin.lock().lines().map_while(Result::ok) {
+        let key = key.trim();
+        if key.is_empty() {
+            continue;
         }
-        if j > 0 && vals[i][j - 1] {
-            stack.push((i, j - 1));
-        }
-        vals[i][j] = false;
+        let grid = Grid::hash(key, height);
+        println!("{} {} {}", key, grid.used(), grid.count_regions());
     }
 }
 
-fn count_regions(mut vals: Vec<Vec<bool>>) -> u32 {
-    let mut res = 0;
-    let length = vals.len();
-    for i in 0..length {
-        let width = vals[i].len();
-        for j in 0..width {
-            if vals[i][j] {
-                mark_region(&mut vals, i, j);
-                res += 1;
-            }
+fn main() {
+    let mut key = "oundnydw".to_owned();
+    let mut height = 128;
+    let mut mode = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--key" => key = args.next().unwrap_or(key),
+            "--height" => height = args.next().and_then(|s| s.parse().ok()).unwrap_or(height),
+            _ => mode.push(arg),
         }
     }
-    res
-}
 
-fn main() {
-    let input = "oundnydw";
-    let mut total_bits_set = 0;
-    let mut values = Vec::new();
-    for i in 0..128 {
-        let line = format!("{}-{}", input, i);
-        let hashvalue = hash(line);
-        for val in hashvalue.iter() {
-            total_bits_set += val.count_ones();
+    if mode.first().map(String::as_str) == Some("bulk") {
+        return bulk(height);
+    }
+    let grid = Grid::hash(&key, height);
+    match mode.first().map(String::as_str) {
+        Some("pbm") => print!("{}", regions::label(&grid.to_bools()).to_pbm()),
+        Some("ppm") => print!("{}", regions::label(&grid.to_bools()).to_ppm()),
+        Some("regions") => {
+            let labels = regions::label(&grid.to_bools());
+            println!("region  size  rows      columns");
+            for (id, region) in labels.regions.iter().enumerate() {
+                println!("{:>6}  {:>4}  {:>3}-{:<3}   {:>3}-{:<3}",
+                         id, region.size, region.top, region.bottom, region.left, region.right);
+            }
+            if let Some((id, region)) = labels.largest() {
+                println!("largest region: {} with {} squares", id, region.size);
+            }
+        }
+        _ => {
+            println!("total bits used: {}", grid.used());
+            println!("total number of regions: {}", grid.count_regions());
         }
-        values.push(hash_to_bitvec(&hashvalue));
     }
-    println!("total bits used: {}", total_bits_set);
-    println!("total number of regions: {}", count_regions(values));
 }
@@ -0,0 +1,179 @@
+use disjointset::DisjointSet;
+
+// a connected group of used squares. rows and columns of the bounding box are inclusive
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub size: usize,
+    pub top: usize,
+    pub left: usize,
+    pub bottom: usize,
+    pub right: usize,
+}
+
+// the region of every square, None for free ones. regions are numbered in the order
+// their first square is met, reading row by row
+pub struct Labels {
+    pub labels: Vec<Vec<Option<usize>>>,
+    pub regions: Vec<Region>,
+}
+
+// two passes: join every used square with the used squares above and to the left of it,
+// then give each set a region number. the grid itself is left alone
+pub fn label(grid: &[Vec<bool>]) -> Labels {
+    let width = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+    let index = |i: usize, j: usize| i * width + j;
+    let used = |i: usize, j: usize| grid[i].get(j).cloned().unwrap_or(false);
+    let mut sets = DisjointSet::new(grid.len() * width);
+    for i in 0..grid.len() {
+        for j in 0..width {
+            if !used(i, j) {
+                continue;
+            }
+            if i > 0 && used(i - 1, j) {
+                sets.union(index(i, j), index(i - 1, j));
+            }
+            if j > 0 && used(i, j - 1) {
+                sets.union(index(i, j), index(i, j - 1));
+            }
+        }
+    }
+
+    let mut number = vec![None; grid.len() * width];
+    let mut regions: Vec<Region> = Vec::new();
+    let mut labels = vec![vec![None; width]; grid.len()];
+    for (i, row) in labels.iter_mut().enumerate() {
+        for (j, label) in row.iter_mut().enumerate() {
+            if !used(i, j) {
+                continue;
+            }
+            let root = sets.find(index(i, j));
+            let id = match number[root] {
+                Some(id) => id,
+                None => {
+                    number[root] = Some(regions.len());
+                    regions.push(Region {
+                        size: 0,
+                        top: i,
+                        left: j,
+                        bottom: i,
+                        right: j,
+                    });
+                    regions.len() - 1
+                }
+            };
+            let region = &mut regions[id];
+            region.size += 1;
+            region.left = region.left.min(j);
+            region.right = region.right.max(j);
+            region.bottom = i;
+            *label = Some(id);
+        }
+    }
+    Labels { labels, regions }
+}
+
+impl Labels {
+    // the first of the largest regions
+    pub fn largest(&self) -> Option<(usize, &Region)> {
+        self.regions
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|&(_, region)| region.size)
+    }
+
+    // plain PBM, one character per square
+    pub fn to_pbm(&self) -> String {
+        let mut out = format!("P1\n{} {}\n", self.width(), self.labels.len());
+        for row in self.labels.iter() {
+            push_wrapped(&mut out, row.iter().map(|l| if l.is_some() { "1" } else { "0" }));
+        }
+        out
+    }
+
+    // plain PPM with free squares black and every region in its own colour
+    pub fn to_ppm(&self) -> String {
+        let mut out = format!("P3\n{} {}\n255\n", self.width(), self.labels.len());
+        for row in self.labels.iter() {
+            let channels = row.iter().flat_map(|l| {
+                let (r, g, b) = l.map_or((0, 0, 0), colour);
+                vec![r.to_string(), g.to_string(), b.to_string()]
+            });
+            push_wrapped(&mut out, channels);
+        }
+        out
+    }
+
+    fn width(&self) -> usize {
+        self.labels.first().map_or(0, |row| row.len())
+    }
+}
+
+// plain formats don't allow lines longer than this
+const LINE_WIDTH: usize = 70;
+
+// one row of an image, separated by spaces and broken over as many lines as it needs
+fn push_wrapped<S: AsRef<str>, I: Iterator<Item = S>>(out: &mut String, values: I) {
+    let mut line = 0;
+    for value in values {
+        let value = value.as_ref();
+        if line > 0 && line + 1 + value.len() > LINE_WIDTH {
+            out.push('\n');
+            line = 0;
+        }
+        if line > 0 {
+            out.push(' ');
+            line += 1;
+        }
+        out.push_str(value);
+        line += value.len();
+    }
+    out.push('\n');
+}
+
+// hues spaced by the golden angle, so neighbouring region numbers look nothing alike
+fn colour(id: usize) -> (u8, u8, u8) {
+    let hue = (id as f64 * 0.618_033_988_75).fract() * 6.0;
+    let value = if id.is_multiple_of(2) { 1.0 } else { 0.75 };
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    let (r, g, b) = match hue as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    let channel = |c: f64| (c * value * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> Vec<Vec<bool>> {
+        rows.iter().map(|row| row.chars().map(|c| c == '#').collect()).collect()
+    }
+
+    #[test]
+    fn labels() {
+        let labels = label(&grid(&["##..#", "..#.#", "###.#"]));
+        let sizes: Vec<usize> = labels.regions.iter().map(|r| r.size).collect();
+        assert_eq!(sizes, [2, 3, 4]);
+        assert_eq!(labels.labels[1][2], Some(2));
+        let r = &labels.regions[2];
+        assert_eq!((r.top, r.left, r.bottom, r.right), (1, 0, 2, 2));
+        assert_eq!(labels.largest().map(|(id, _)| id), Some(2));
+    }
+
+    #[test]
+    fn plain_lines_are_short() {
+        let labels = label(&vec![vec![true; 128]; 3]);
+        for image in [labels.to_pbm(), labels.to_ppm()].iter() {
+            assert!(image.lines().all(|line| line.len() <= LINE_WIDTH));
+        }
+        // each image row still starts on a line of its own
+        assert_eq!(labels.to_pbm().lines().count(), 2 + 3 * 4);
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt::{self, Write};
+use std::hash::Hasher;
+
+pub const LIST_SIZE: usize = 256;
+pub const ROUNDS: usize = 64;
+pub const SUFFIX: [u8; 5] = [17, 31, 73, 47, 23];
+pub const BLOCK_SIZE: usize = 16;
+
+pub fn reverse<T>(list: &mut [T], mut start: usize, mut len: usize) {
+    while len > 1 {
+        start %= list.len();
+        let endpos = (start + len - 1) % list.len();
+        list.swap(start, endpos);
+        start += 1;
+        len -= 2;
+    }
+}
+
+// the circular list, along with the position and skip size carried between rounds
+#[derive(Debug, Clone)]
+pub struct Knot {
+    pub list: Vec<u32>,
+    pos: usize,
+    skip: usize,
+}
+
+impl Knot {
+    pub fn new(size: usize) -> Knot {
+        Knot {
+            list: (0..size as u32).collect(),
+            pos: 0,
+            skip: 0,
+        }
+    }
+
+    // keeps the list rotated so every span is contiguous: `rotation` is the position held
+    // at index 0, and a span that would run past the end first rotates the list just far
+    // enough to fit. the list is put back in order at the end of the round.
+    // every length must be at most the size of the list
+    pub fn round(&mut self, lengths: &[usize]) {
+        let size = self.list.len();
+        let mut rotation = 0;
+        for &length in lengths {
+            assert!(length <= size, "length {} does not fit in a list of {}", length, size);
+            let mut start = (self.pos + size - rotation) % size;
+            if start + length > size {
+                let shift = start + length - size;
+                self.list.rotate_left(shift);
+                rotation = (rotation + shift) % size;
+                start -= shift;
+            }
+            self.list[start..start + length].reverse();
+            self.pos = (self.pos + length + self.skip) % size;
+            self.skip += 1;
+        }
+        self.list.rotate_right(rotation);
+    }
+
+    // swaps one pair at a time with `reverse`; slower, kept to check `round` against
+    pub fn round_reference(&mut self, lengths: &[usize]) {
+        for &length in lengths {
+            reverse(&mut self.list, self.pos, length);
+            self.pos = (self.pos + length + self.skip) % self.list.len();
+            self.skip += 1;
+        }
+    }
+
+    // xor of every block of values
+    pub fn dense(&self, block_size: usize) -> Vec<u32> {
+        self.list
+            .chunks(block_size)
+            .map(|block| block.iter().fold(0, |a, &b| a ^ b))
+            .collect()
+    }
+}
+
+// everything that can be varied about a knot hash; the default is the standard hash
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnotHashParams {
+    pub list_size: usize,
+    pub rounds: usize,
+    pub suffix: Vec<u8>,
+    pub block_size: usize,
+}
+
+impl Default for KnotHashParams {
+    fn default() -> KnotHashParams {
+        KnotHashParams {
+            list_size: LIST_SIZE,
+            rounds: ROUNDS,
+            suffix: SUFFIX.to_vec(),
+            block_size: BLOCK_SIZE,
+        }
+    }
+}
+
+impl KnotHashParams {
+    // the lengths for a full hash: the input bytes followed by the suffix
+    pub fn lengths(&self, input: &[u8]) -> Vec<usize> {
+        input.iter().chain(self.suffix.iter()).map(|&b| b as usize).collect()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.list_size == 0 || self.list_size > u32::MAX as usize {
+            return Err(format!("list size {} is out of range", self.list_size));
+        }
+        if self.block_size == 0 {
+            return Err("block size must be at least 1".to_owned());
+        }
+        Ok(())
+    }
+
+    fn hash_with<F>(&self, input: &[u8], round: F) -> Result<Vec<u32>, String>
+    where
+        F: Fn(&mut Knot, &[usize]),
+    {
+        self.validate()?;
+        let lengths = self.lengths(input);
+        if let Some(&length) = lengths.iter().find(|&&l| l > self.list_size) {
+            return Err(format!("length {} does not fit in a list of {}", length, self.list_size));
+        }
+        let mut knot = Knot::new(self.list_size);
+        for _ in 0..self.rounds {
+            round(&mut knot, &lengths);
+        }
+        Ok(knot.dense(self.block_size))
+    }
+
+    // the dense hash, one value per block
+    pub fn hash(&self, input: &[u8]) -> Result<Vec<u32>, String> {
+        self.hash_with(input, Knot::round)
+    }
+
+    // the same hash computed with `Knot::round_reference`
+    pub fn hash_reference(&self, input: &[u8]) -> Result<Vec<u32>, String> {
+        self.hash_with(input, Knot::round_reference)
+    }
+
+    // enough hex digits per block to hold any value in the list
+    pub fn hex_width(&self) -> usize {
+        let mut width = 1;
+        while self.list_size > 1 << (4 * width) {
+            width += 1;
+        }
+        width
+    }
+
+    pub fn hash_hex(&self, input: &[u8]) -> Result<String, String> {
+        let width = self.hex_width();
+        let mut result = String::new();
+        for value in self.hash(input)? {
+            write!(&mut result, "{:0width$x}", value, width = width).unwrap();
+        }
+        Ok(result)
+    }
+}
+
+pub fn hash(input: &[u8]) -> Digest {
+    // the default parameters always fit: every byte length is at most 255
+    let dense = KnotHashParams::default().hash(input).unwrap();
+    let mut bytes = [0; LIST_SIZE / BLOCK_SIZE];
+    for (byte, value) in bytes.iter_mut().zip(dense) {
+        *byte = value as u8;
+    }
+    Digest(bytes)
+}
+
+pub fn to_hex_string(bytes: &[u8]) -> String {
+    let mut result = String::new();
+    for value in bytes {
+        write!(&mut result, "{:02x}", value).unwrap();
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Digest(pub [u8; 16]);
+
+impl Digest {
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex_string(&self.0)
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+// streaming interface. every round replays the whole input, so it is buffered until
+// `finalize`; the hash of the concatenated updates equals the hash of the whole input
+#[derive(Debug, Clone, Default)]
+pub struct KnotHasher {
+    input: Vec<u8>,
+}
+
+impl KnotHasher {
+    pub fn new() -> KnotHasher {
+        KnotHasher::default()
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.input.extend_from_slice(bytes);
+    }
+
+    pub fn finalize(self) -> Digest {
+        hash(&self.input)
+    }
+}
+
+impl Hasher for KnotHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    // the first 8 bytes of the digest, big endian
+    fn finish(&self) -> u64 {
+        let digest = hash(&self.input);
+        digest.0[..8].iter().fold(0, |acc, &b| (acc << 8) | b as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VECTORS: [(&str, &str); 4] = [
+        ("", "a2582a3a0e66e6e86e3812dcb672a272"),
+        ("AoC 2017", "33efeb34ea91902bb2f59c9920caa6cd"),
+        ("1,2,3", "3efbe78a8d82f29979031a4aa0b16a9d"),
+        ("1,2,4", "63960835bcdc130f0b66d7ff4f6a5a8e"),
+    ];
+
+    #[test]
+    fn published_vectors() {
+        for &(input, expected) in VECTORS.iter() {
+            assert_eq!(hash(input.as_bytes()).to_hex(), expected, "input {:?}", input);
+            assert_eq!(KnotHashParams::default().hash_hex(input.as_bytes()).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn split_updates() {
+        let input = b"AoC 2017, split across several updates";
+        for pieces in 1..6 {
+            let mut hasher = KnotHasher::new();
+            for chunk in input.chunks(input.len() / pieces + 1) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize(), hash(input));
+        }
+        let mut hasher = KnotHasher::new();
+        hasher.update(b"");
+        assert_eq!(hasher.finalize(), hash(b""));
+    }
+
+    #[test]
+    fn hasher_finish() {
+        let mut hasher = KnotHasher::new();
+        hasher.write(b"AoC ");
+        hasher.write(b"2017");
+        assert_eq!(hasher.finish(), 0x33ef_eb34_ea91_902b);
+        // finishing doesn't consume the input, so more can still be written
+        hasher.write(b"");
+        assert_eq!(hasher.finish(), 0x33ef_eb34_ea91_902b);
+        assert_eq!(KnotHasher::new().finish(), 0xa258_2a3a_0e66_e6e8);
+    }
+
+    #[test]
+    fn round_matches_reference() {
+        // xorshift, so the lengths are the same on every run
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut random = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        for size in (1..20).chain([255, 256, 257].iter().cloned()) {
+            let lengths: Vec<usize> = (0..random(50)).map(|_| random(size + 1)).collect();
+            let mut fast = Knot::new(size);
+            let mut reference = Knot::new(size);
+            for _ in 0..3 {
+                fast.round(&lengths);
+                reference.round_reference(&lengths);
+                assert_eq!(fast.list, reference.list, "size {}, lengths {:?}", size, lengths);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit")]
+    fn round_rejects_long_lengths() {
+        Knot::new(5).round(&[6]);
+    }
+}